@@ -0,0 +1,92 @@
+//! Runtime API for launching an onion service without any on-disk configuration.
+//!
+//! The `hss` keystore tooling manages long-lived, on-disk service identities
+//! configured ahead of time. Embedders that want the `ADD_ONION`-style
+//! control-port workflow -- generate an identity, publish it, and start
+//! accepting connections, all without a separate process or a pre-existing
+//! config file -- have had no programmatic equivalent. This module adds one.
+
+use std::sync::Arc;
+
+use tor_error::ErrorReport as _;
+use tor_hsservice::{HsIdKeypair, OnionServiceConfig, RunningOnionService, StreamRequest};
+use tor_keymgr::KeystoreSelector;
+use tor_rtcompat::Runtime;
+
+use futures::stream::BoxStream;
+
+use crate::{Error, Result, TorAddr, TorClient};
+
+/// Where (if anywhere) to persist the identity key of an ephemeral service.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum EphemeralKeyPersistence {
+    /// Keep the identity key in memory only; it is lost when the
+    /// [`RunningOnionService`] is dropped.
+    #[default]
+    InMemoryOnly,
+    /// Also write the identity key into the client's configured keystore,
+    /// under `nickname`, so it can later be migrated or reused via the same
+    /// `ctor-migrate` machinery used for on-disk services.
+    Persist {
+        /// The nickname to store the key under.
+        nickname: String,
+    },
+}
+
+/// Configuration for [`TorClient::launch_onion_service_ephemeral`].
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct EphemeralOnionServiceConfig {
+    /// The underlying service configuration (ports, anti-DoS settings, etc).
+    pub service_config: OnionServiceConfig,
+    /// Whether (and how) to persist the generated identity key.
+    pub persistence: EphemeralKeyPersistence,
+    /// An existing identity key to publish instead of generating a new one.
+    ///
+    /// Useful for restarting a service under the same address without having
+    /// persisted the key (e.g. the caller stores it themselves).
+    pub identity: Option<HsIdKeypair>,
+}
+
+impl<R: Runtime> TorClient<R> {
+    /// Generate (or accept) an identity key entirely in memory, publish
+    /// descriptors for it, and hand back a stream of inbound connections.
+    ///
+    /// The service is torn down -- its descriptors withdrawn and its
+    /// introduction points closed -- when the returned [`RunningOnionService`]
+    /// is dropped. This is the programmatic equivalent of a control port's
+    /// `ADD_ONION` command: no on-disk configuration is required. The
+    /// service always goes away with the handle; its identity key does too,
+    /// unless [`EphemeralKeyPersistence::Persist`] was requested.
+    pub fn launch_onion_service_ephemeral(
+        &self,
+        config: EphemeralOnionServiceConfig,
+    ) -> Result<(TorAddr, Arc<RunningOnionService>, BoxStream<'static, StreamRequest>)> {
+        let identity = match config.identity {
+            Some(key) => key,
+            None => HsIdKeypair::generate_ephemeral()
+                .map_err(|e| Error::from_proto(e.report().to_string()))?,
+        };
+
+        let keystore_selector = match &config.persistence {
+            EphemeralKeyPersistence::InMemoryOnly => KeystoreSelector::Ephemeral,
+            EphemeralKeyPersistence::Persist { nickname } => {
+                KeystoreSelector::primary_for_nickname(nickname)
+            }
+        };
+
+        let (service, rendezvous) = tor_hsservice::launch_onion_service_with_identity(
+            self.dormant_circmgr_handle(),
+            config.service_config,
+            identity,
+            keystore_selector,
+        )
+        .map_err(|e| Error::from_proto(e.report().to_string()))?;
+
+        let addr = TorAddr::from_onion_address(service.onion_address())
+            .map_err(|e| Error::from_proto(e.report().to_string()))?;
+
+        Ok((addr, service, rendezvous))
+    }
+}
@@ -0,0 +1,101 @@
+/// `futures-io` io adapter for arti-hyper, so the crate can run without tokio.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use hyper::rt::{Read as HRead, Write as HWrite};
+
+use crate::IoAdapter;
+
+#[derive(Clone, Debug)]
+pub struct FuturesIoAdapter;
+
+impl<S> IoAdapter<S> for FuturesIoAdapter
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Io = FuturesCompat<S>;
+
+    fn adapt(&self, stream: S) -> Self::Io {
+        FuturesCompat(stream)
+    }
+}
+
+#[derive(Debug)]
+pub struct FuturesCompat<S>(pub S);
+
+impl<S: AsyncRead + Unpin> HRead for FuturesCompat<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        // `futures::io::AsyncRead` only accepts an initialized `&mut [u8]`,
+        // unlike hyper's uninitialized `ReadBufCursor`, so zero the
+        // destination before handing it over.
+        let dst = unsafe { buf.as_mut() };
+        for byte in dst.iter_mut() {
+            byte.write(0);
+        }
+        let dst = unsafe { std::mem::MaybeUninit::slice_assume_init_mut(dst) };
+
+        match Pin::new(&mut self.get_mut().0).poll_read(cx, dst) {
+            Poll::Ready(Ok(n)) => {
+                unsafe { buf.advance(n) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> HWrite for FuturesCompat<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        // hyper's `poll_shutdown` is futures' `poll_close`.
+        Pin::new(&mut self.get_mut().0).poll_close(cx)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FuturesCompat<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FuturesCompat<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_close(cx)
+    }
+}
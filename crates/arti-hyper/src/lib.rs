@@ -1,22 +1,63 @@
-use std::{pin::Pin, future::Future, io, task::{Context, Poll}};
+use std::{pin::Pin, future::Future, io, task::{Context, Poll}, time::Duration};
 use hyper::rt::{Read, Write};
 use tower_service::Service;
 use http::Uri;
-use arti_client::{TorClient, IntoTorAddr};
-use tor_rtcompat::Runtime;
+use futures::{future::{select, Either}, stream::FuturesUnordered, StreamExt};
+use arti_client::{IsolationToken, TorClient, IntoTorAddr, StreamPrefs};
+use tor_rtcompat::{Runtime, SleepProvider};
 use tor_proto::client::stream::DataStream;
 
+/// Default number of circuits raced against each other per connection attempt.
+///
+/// Chosen to match hyper's own Happy-Eyeballs connect layer, which races a
+/// small handful of candidates rather than every one available.
+const DEFAULT_CONNECTION_ATTEMPTS: usize = 2;
+
+/// Default delay between launching successive racing attempts.
+const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
 #[cfg(feature="tokio")]
 pub mod io_adapter_tokio;
 
+#[cfg(feature="futures-io")]
+pub mod io_adapter_futures;
+
 #[cfg(all(feature="tokio", feature="rustls"))]
 pub mod tls_rustls_tokio;
 
+#[cfg(all(feature="tokio", feature="native-tls"))]
+pub mod tls_native_tls_tokio;
+
+pub mod rewind;
+use rewind::Rewind;
+
+#[cfg(feature="http2")]
+pub mod pool;
+
 pub trait IoAdapter<S>: Send + Sync + 'static {
     type Io: Read + Write + Send + Unpin + 'static;
     fn adapt(&self, stream: S) -> Self::Io;
 }
 
+/// Reports the protocol a `TlsUpgrader::Io` negotiated via ALPN, if any.
+///
+/// Implemented by TLS backends that support ALPN; plain (non-TLS) `Io`s get
+/// the default `None`, which callers should treat as "assume HTTP/1.1".
+pub trait NegotiatedAlpn {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<P, T: NegotiatedAlpn> NegotiatedAlpn for MaybeTls<P, T> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        match self {
+            MaybeTls::Plain(_) => None,
+            MaybeTls::Tls(t) => t.alpn_protocol(),
+        }
+    }
+}
+
 pub enum TlsMode { Plain, Tls }
 
 pub enum MaybeTls<Plain, Tls> {
@@ -92,16 +133,69 @@ pub struct ArtiHttpConnector<R: Runtime, A, T> {
     client: TorClient<R>,
     io_adapter: A,
     tls: T,
+    /// How many circuits to race against each other per `call`. `1` disables racing.
+    connection_attempts: usize,
+    /// How long to wait before launching the next racing attempt.
+    attempt_delay: Duration,
+    /// If set, tags every connection with this token, both on `StreamPrefs`
+    /// (so Tor groups their circuits together) and on the resulting
+    /// `Connected::extra` (so a pool never hands one caller's connection
+    /// to another with a different token).
+    isolation: Option<IsolationToken>,
 }
 
 impl<R: Runtime, A, T> ArtiHttpConnector<R, A, T> {
     pub fn new(client: TorClient<R>, io_adapter: A, tls: T) -> Self {
-        Self { client, io_adapter, tls }
+        Self {
+            client,
+            io_adapter,
+            tls,
+            connection_attempts: DEFAULT_CONNECTION_ATTEMPTS,
+            attempt_delay: DEFAULT_ATTEMPT_DELAY,
+            isolation: None,
+        }
+    }
+
+    /// Race up to `n` connection attempts, each on its own isolated circuit,
+    /// and use whichever completes first. `n == 1` disables racing.
+    pub fn with_connection_attempts(mut self, n: usize) -> Self {
+        self.connection_attempts = n.max(1);
+        self
+    }
+
+    /// Set the stagger delay between launching successive racing attempts.
+    pub fn with_attempt_delay(mut self, delay: Duration) -> Self {
+        self.attempt_delay = delay;
+        self
+    }
+
+    /// Tag every connection this connector makes with `token`: Tor is asked
+    /// to group their circuits together via `StreamPrefs`, and the resulting
+    /// `Connected::extra` carries the token so a pool built on
+    /// [`ArtiHttpConnection::connected`] treats connections with different
+    /// tokens as non-interchangeable. Gives one-circuit-per-identity
+    /// behavior (e.g. per-tab or per-account) while still reusing keep-alive
+    /// within an identity.
+    pub fn isolated(mut self, token: IsolationToken) -> Self {
+        self.isolation = Some(token);
+        self
+    }
+
+    /// The isolation token this connector tags every connection with, if any.
+    ///
+    /// Used by [`crate::pool::ArtiHttpClient`] to key its connection cache,
+    /// so connections made under different tokens are never handed out
+    /// interchangeably.
+    pub(crate) fn isolation(&self) -> Option<IsolationToken> {
+        self.isolation
     }
 }
 
 pub struct ArtiHttpConnection<Io> {
     io: Io,
+    /// The isolation token this connection was made with, if any; surfaced
+    /// to connection pools via [`Connection::connected`]'s `extra`.
+    isolation: Option<IsolationToken>,
 }
 
 impl<Io: Read + Unpin> Read for ArtiHttpConnection<Io> {
@@ -143,7 +237,17 @@ impl<Io: Read + Write + Send + Unpin + 'static> hyper_util::client::legacy::conn
     for ArtiHttpConnection<Io>
 {
     fn connected(&self) -> hyper_util::client::legacy::connect::Connected {
-        hyper_util::client::legacy::connect::Connected::new()
+        let connected = hyper_util::client::legacy::connect::Connected::new();
+        match self.isolation {
+            Some(token) => connected.extra(token),
+            None => connected,
+        }
+    }
+}
+
+impl<Io: NegotiatedAlpn> NegotiatedAlpn for ArtiHttpConnection<Io> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.io.alpn_protocol()
     }
 }
 
@@ -151,9 +255,9 @@ impl<R, A, T> Service<Uri> for ArtiHttpConnector<R, A, T>
 where
     R: Runtime + Clone + Send + Sync + 'static,
     A: IoAdapter<DataStream> + Clone,
-    T: TlsUpgrader<<A as IoAdapter<DataStream>>::Io> + Clone,
+    T: TlsUpgrader<Rewind<<A as IoAdapter<DataStream>>::Io>> + Clone,
 {
-    type Response = ArtiHttpConnection<<T as TlsUpgrader<<A as IoAdapter<DataStream>>::Io>>::Io>;
+    type Response = ArtiHttpConnection<<T as TlsUpgrader<Rewind<<A as IoAdapter<DataStream>>::Io>>>::Io>;
     type Error = io::Error;
     type Future = Pin<Box<dyn Future<Output=Result<Self::Response, Self::Error>> + Send>>;
 
@@ -164,6 +268,9 @@ where
         let client = self.client.clone();
         let io_adapter = self.io_adapter.clone();
         let tls = self.tls.clone();
+        let connection_attempts = self.connection_attempts;
+        let attempt_delay = self.attempt_delay;
+        let isolation = self.isolation;
 
         Box::pin(async move {
             let host = uri.host().ok_or_else(|| io_err("missing host"))?.to_string();
@@ -174,15 +281,89 @@ where
 
             let addr = (host.clone(), port).into_tor_addr()
                 .map_err(|_| io_err("invalid address"))?;
-            let arti_stream = client.connect(addr).await
-                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()))?;
 
-            let io = io_adapter.adapt(arti_stream);
+            let mut prefs = StreamPrefs::new();
+            if let Some(token) = isolation {
+                prefs.set_isolation_group(token);
+            }
+
+            let arti_stream = if connection_attempts <= 1 {
+                client.connect_with_prefs(addr, &prefs).await
+                    .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()))?
+            } else {
+                race_connections(&client, &addr, connection_attempts, attempt_delay, isolation).await?
+            };
+
+            // Wrapped in `Rewind` so a `TlsUpgrader` can peek at the first
+            // few bytes (to sniff a ClientHello, dispatch on ALPN, etc.)
+            // and push them back rather than consuming them outright.
+            let io = Rewind::new(io_adapter.adapt(arti_stream));
             let io = tls.upgrade(&host, io, tls_mode).await?;
-            Ok(ArtiHttpConnection { io })
+            Ok(ArtiHttpConnection { io, isolation })
         })
     }
 }
 
+/// Race up to `attempts` connections to `addr` against each other, each
+/// forced onto its own circuit, launching one every `attempt_delay` until
+/// either one succeeds or all have been launched and failed.
+///
+/// Returns the first successful [`DataStream`], or the last error seen if
+/// every attempt failed.
+async fn race_connections<R: Runtime>(
+    client: &TorClient<R>,
+    addr: &(impl arti_client::IntoTorAddr + Clone),
+    attempts: usize,
+    attempt_delay: Duration,
+    isolation: Option<IsolationToken>,
+) -> io::Result<DataStream> {
+    let mut in_flight = FuturesUnordered::new();
+    let mut last_err = None;
+
+    for attempt in 0..attempts {
+        let mut prefs = StreamPrefs::new();
+        // Each racing attempt still needs its own circuit regardless of the
+        // caller's isolation token, or they'd all race the same circuit.
+        prefs.isolate_every_stream();
+        if let Some(token) = isolation {
+            prefs.set_isolation_group(token);
+        }
+        let addr = addr.clone();
+        let client = client.clone();
+        in_flight.push(async move { client.connect_with_prefs(addr, &prefs).await });
+
+        // Don't wait out the stagger delay after launching the last attempt.
+        if attempt + 1 == attempts {
+            continue;
+        }
+
+        let sleep = client.runtime().sleep(attempt_delay);
+        futures::pin_mut!(sleep);
+        loop {
+            match select(in_flight.next(), sleep).await {
+                Either::Left((Some(Ok(stream)), _)) => return Ok(stream),
+                Either::Left((Some(Err(e)), remaining_sleep)) => {
+                    last_err = Some(e);
+                    sleep = remaining_sleep;
+                    continue;
+                }
+                Either::Left((None, _)) | Either::Right(_) => break,
+            }
+        }
+    }
+
+    while let Some(result) = in_flight.next().await {
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.map_or_else(
+        || io_err("all racing connection attempts failed"),
+        |e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()),
+    ))
+}
+
 fn io_err(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, msg) }
 
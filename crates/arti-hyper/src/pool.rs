@@ -0,0 +1,221 @@
+//! ALPN-aware HTTP/1.1 or HTTP/2 dispatch over [`ArtiHttpConnector`], with
+//! connections pooled per `(host, port, isolation token)`.
+//!
+//! `hyper_util`'s own legacy pool multiplexes on [`Connection::connected`],
+//! but doesn't know how to pick between an HTTP/1.1 and an HTTP/2
+//! `SendRequest` for the same transport. `ArtiHttpClient` sits in front of
+//! [`ArtiHttpConnector`] instead: it performs the handshake itself, inspects
+//! the negotiated ALPN protocol, and caches the resulting `SendRequest` so
+//! that repeated calls to the same `(host, port)` reuse one connection
+//! (with HTTP/2 multiplexing many requests over it) rather than opening a
+//! fresh Tor circuit every time.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use arti_client::IsolationToken;
+use bytes::Bytes;
+use http::Uri;
+use http_body::Body;
+use hyper::client::conn::{http1, http2};
+use hyper::rt::{Read, Write};
+use tor_rtcompat::Runtime;
+use tower_service::Service;
+
+use crate::rewind::Rewind;
+use crate::{ArtiHttpConnection, ArtiHttpConnector, IoAdapter, NegotiatedAlpn, TlsUpgrader};
+
+/// The key a pooled connection is cached under: destination plus isolation
+/// token, so a connector configured with one token never hands a caller a
+/// connection that was made (or will be made) under a different one.
+type PoolKey = (String, u16, Option<IsolationToken>);
+
+/// A handle for sending requests that is either an HTTP/1.1 or HTTP/2 sender.
+///
+/// HTTP/2's `SendRequest` is already `Clone` and safe to use concurrently
+/// from multiple callers, so pooled HTTP/2 connections are shared; pooled
+/// HTTP/1.1 connections are not (one request at a time per connection).
+#[derive(Clone)]
+enum Sender<B> {
+    Http1(Arc<Mutex<http1::SendRequest<B>>>),
+    Http2(http2::SendRequest<B>),
+}
+
+impl<B> Sender<B> {
+    /// Whether the connection behind this sender has already gone away (the
+    /// driving `connection` future finished, e.g. because the peer closed
+    /// it), meaning `send_request` would just fail if called now.
+    fn is_closed(&self) -> bool {
+        match self {
+            Sender::Http1(send) => send.lock().expect("poisoned").is_closed(),
+            Sender::Http2(send) => send.is_closed(),
+        }
+    }
+}
+
+/// An executor adapter satisfying `hyper::rt::Executor`, reusing the Arti runtime.
+#[derive(Clone)]
+struct RuntimeExecutor<R>(R);
+
+impl<R, F> hyper::rt::Executor<F> for RuntimeExecutor<R>
+where
+    R: Runtime,
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        let _ = self.0.spawn_obj(Box::pin(async move {
+            let _ = fut.await;
+        }).into());
+    }
+}
+
+/// An ALPN-negotiating, connection-pooling HTTP client built on [`ArtiHttpConnector`].
+pub struct ArtiHttpClient<R: Runtime, A, T> {
+    connector: ArtiHttpConnector<R, A, T>,
+    runtime: R,
+    pool: Arc<Mutex<HashMap<PoolKey, Sender<hyper::body::Incoming>>>>,
+}
+
+impl<R: Runtime, A, T> ArtiHttpClient<R, A, T> {
+    pub fn new(connector: ArtiHttpConnector<R, A, T>, runtime: R) -> Self {
+        Self {
+            connector,
+            runtime,
+            pool: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<R, A, T> ArtiHttpClient<R, A, T>
+where
+    R: Runtime + Clone + Send + Sync + 'static,
+    A: IoAdapter<tor_proto::client::stream::DataStream> + Clone,
+    T: TlsUpgrader<Rewind<<A as IoAdapter<tor_proto::client::stream::DataStream>>::Io>> + Clone,
+    <T as TlsUpgrader<Rewind<<A as IoAdapter<tor_proto::client::stream::DataStream>>::Io>>>::Io:
+        NegotiatedAlpn + Read + Write + Send + Unpin + 'static,
+{
+    /// Send `req`, reusing a pooled connection to `req`'s host/port if one exists.
+    pub async fn request(
+        &self,
+        req: http::Request<impl Body<Data = Bytes, Error: Into<Box<dyn std::error::Error + Send + Sync>>> + Send + Unpin + 'static>,
+    ) -> io::Result<http::Response<hyper::body::Incoming>> {
+        let (host, port) = host_port(req.uri())?;
+        let key = (host, port, self.connector.isolation());
+
+        let sender = self.pooled_sender(&key).await?;
+        match sender {
+            Sender::Http1(send) => {
+                let mut send = send.lock().expect("poisoned");
+                send.send_request(req)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            }
+            Sender::Http2(mut send) => send
+                .send_request(req)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+        }
+    }
+
+    /// Get a cached sender for `key`, or dial, handshake, and cache a new one.
+    ///
+    /// A cached sender whose connection has already closed (e.g. the peer
+    /// hung up, or the underlying circuit died) is not reused: it's dropped
+    /// from the pool and a fresh connection is dialed instead, so a cache
+    /// hit never hands back a sender that's certain to fail.
+    async fn pooled_sender(&self, key: &PoolKey) -> io::Result<Sender<hyper::body::Incoming>> {
+        if let Some(sender) = self.pool.lock().expect("poisoned").get(key).cloned() {
+            if !sender.is_closed() {
+                return Ok(sender);
+            }
+        }
+
+        let uri: Uri = format!("//{}:{}", key.0, key.1).parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "bad host/port for dial")
+        })?;
+        let conn = Service::call(&mut self.connector.clone(), uri).await?;
+        let sender = self.handshake(conn).await?;
+
+        self.pool
+            .lock()
+            .expect("poisoned")
+            .insert(key.clone(), sender.clone());
+        Ok(sender)
+    }
+
+    /// Negotiate HTTP/2 or HTTP/1.1 on `conn` depending on the ALPN result, and
+    /// spawn a task to drive the resulting connection.
+    async fn handshake<Io>(&self, conn: ArtiHttpConnection<Io>) -> io::Result<Sender<hyper::body::Incoming>>
+    where
+        Io: NegotiatedAlpn + Read + Write + Send + Unpin + 'static,
+    {
+        if conn.alpn_protocol() == Some(b"h2") {
+            let (send, connection) = http2::Builder::new(RuntimeExecutor(self.runtime.clone()))
+                .handshake(conn)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.runtime.spawn_obj(Box::pin(async move {
+                let _ = connection.await;
+            }).into()).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            Ok(Sender::Http2(send))
+        } else {
+            let (send, connection) = http1::handshake(conn)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.runtime.spawn_obj(Box::pin(async move {
+                let _ = connection.await;
+            }).into()).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            Ok(Sender::Http1(Arc::new(Mutex::new(send))))
+        }
+    }
+}
+
+/// Extract a `(host, port)` pooling key from a request URI.
+fn host_port(uri: &Uri) -> io::Result<(String, u16)> {
+    let host = uri
+        .host()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing host"))?
+        .to_string();
+    let https = uri.scheme_str().unwrap_or("http").eq_ignore_ascii_case("https");
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+    Ok((host, port))
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    // @@ begin test lint list maintained by maint/add_warning @@
+    #![allow(clippy::bool_assert_comparison)]
+    #![allow(clippy::clone_on_copy)]
+    #![allow(clippy::dbg_macro)]
+    #![allow(clippy::mixed_attributes_style)]
+    #![allow(clippy::print_stderr)]
+    #![allow(clippy::print_stdout)]
+    #![allow(clippy::single_char_pattern)]
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::unchecked_duration_subtraction)]
+    #![allow(clippy::useless_vec)]
+    #![allow(clippy::needless_pass_by_value)]
+    //! <!-- @@ end test lint list maintained by maint/add_warning @@ -->
+    use super::*;
+    use crate::io_adapter_tokio::TokioCompat;
+
+    /// A [`Sender`] reports itself closed once its connection has gone away,
+    /// so [`ArtiHttpClient::pooled_sender`] knows to dial a fresh one rather
+    /// than hand back a sender that can only fail.
+    #[tokio::test]
+    async fn http1_sender_reports_closed_after_peer_hangs_up() {
+        let (client_io, server_io) = tokio::io::duplex(1024);
+        let (send, connection) = http1::handshake(TokioCompat(client_io)).await.unwrap();
+        let sender = Sender::<Bytes>::Http1(Arc::new(Mutex::new(send)));
+        assert!(!sender.is_closed());
+
+        drop(server_io);
+        let _ = connection.await;
+
+        assert!(sender.is_closed());
+    }
+}
@@ -0,0 +1,219 @@
+//! A buffered-rewind `Io` wrapper, so a [`TlsUpgrader`](crate::TlsUpgrader)
+//! can peek at the first few bytes of a stream (to sniff a ClientHello, an
+//! ALPN-like preamble, etc.) without losing them.
+//!
+//! Ported from the rewind idea behind hyper's own `Upgraded` type, which
+//! keeps a small prefix buffer that is transparently replayed before
+//! further reads reach the underlying stream.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use hyper::rt::{Read as HRead, Write as HWrite};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+/// Wraps `Io`, allowing bytes already read from it to be pushed back so a
+/// later reader sees them again before anything further off the wire.
+pub struct Rewind<Io> {
+    /// Bytes to replay before reading from `io` again, if any.
+    pre: Option<Bytes>,
+    /// The underlying stream.
+    io: Io,
+}
+
+impl<Io> Rewind<Io> {
+    /// Wrap `io` with an empty rewind buffer.
+    pub fn new(io: Io) -> Self {
+        Self { pre: None, io }
+    }
+
+    /// Push `prefix` back onto the stream, so it is returned by the next
+    /// read(s) before anything further is read from the underlying `io`.
+    ///
+    /// Panics if called while a previously rewound prefix hasn't been fully
+    /// drained yet.
+    pub fn rewind(&mut self, prefix: Bytes) {
+        assert!(self.pre.is_none(), "Rewind::rewind called with prefix already pending");
+        if !prefix.is_empty() {
+            self.pre = Some(prefix);
+        }
+    }
+
+    /// Unwrap this `Rewind`, discarding any not-yet-replayed prefix.
+    pub fn into_inner(self) -> Io {
+        self.io
+    }
+}
+
+impl<Io: HRead + Unpin> HRead for Rewind<Io> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Some(mut prefix) = self.pre.take() {
+            if !prefix.is_empty() {
+                let copy_len = std::cmp::min(prefix.len(), buf.remaining());
+                buf.put_slice(&prefix[..copy_len]);
+                prefix.advance(copy_len);
+                if !prefix.is_empty() {
+                    self.pre = Some(prefix);
+                }
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl<Io: HWrite + Unpin> HWrite for Rewind<Io> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<Io: AsyncRead + Unpin> AsyncRead for Rewind<Io> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Some(mut prefix) = self.pre.take() {
+            if !prefix.is_empty() {
+                let copy_len = std::cmp::min(prefix.len(), buf.remaining());
+                buf.put_slice(&prefix[..copy_len]);
+                prefix.advance(copy_len);
+                if !prefix.is_empty() {
+                    self.pre = Some(prefix);
+                }
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<Io: AsyncRead + Unpin> Rewind<Io> {
+    /// Peek this connection's first two bytes without losing them, and
+    /// report whether they look like the start of a TLS record: `0x16`
+    /// (the handshake content-type) followed by `0x03` (every TLS version's
+    /// major byte).
+    ///
+    /// This is what lets a [`TlsUpgrader`](crate::TlsUpgrader) catch a peer
+    /// sending a TLS ClientHello on a connection that was requested as
+    /// plaintext (`TlsMode::Plain`) -- a case the URI scheme alone can't
+    /// distinguish from ordinary plaintext traffic. The peeked bytes are
+    /// always pushed back via [`rewind`](Self::rewind), so the caller sees
+    /// them again on its next read regardless of the verdict.
+    pub async fn peek_looks_like_tls_handshake(&mut self) -> std::io::Result<bool> {
+        let mut probe = [0u8; 2];
+        let mut filled = 0;
+        while filled < probe.len() {
+            let n = AsyncReadExt::read(self, &mut probe[filled..]).await?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled > 0 {
+            self.rewind(Bytes::copy_from_slice(&probe[..filled]));
+        }
+        Ok(filled == probe.len() && probe[0] == 0x16 && probe[1] == 0x03)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<Io: AsyncWrite + Unpin> AsyncWrite for Rewind<Io> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    // @@ begin test lint list maintained by maint/add_warning @@
+    #![allow(clippy::bool_assert_comparison)]
+    #![allow(clippy::clone_on_copy)]
+    #![allow(clippy::dbg_macro)]
+    #![allow(clippy::mixed_attributes_style)]
+    #![allow(clippy::print_stderr)]
+    #![allow(clippy::print_stdout)]
+    #![allow(clippy::single_char_pattern)]
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::unchecked_duration_subtraction)]
+    #![allow(clippy::useless_vec)]
+    #![allow(clippy::needless_pass_by_value)]
+    //! <!-- @@ end test lint list maintained by maint/add_warning @@ -->
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn detects_tls_handshake_prefix_and_rewinds_it() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        writer.write_all(&[0x16, 0x03, 0x01, 0x00, 0x05]).await.unwrap();
+        let mut io = Rewind::new(reader);
+
+        assert!(io.peek_looks_like_tls_handshake().await.unwrap());
+
+        let mut rest = [0u8; 5];
+        io.read_exact(&mut rest).await.unwrap();
+        assert_eq!(rest, [0x16, 0x03, 0x01, 0x00, 0x05]);
+    }
+
+    #[tokio::test]
+    async fn passes_through_ordinary_plaintext() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        writer.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let mut io = Rewind::new(reader);
+
+        assert!(!io.peek_looks_like_tls_handshake().await.unwrap());
+
+        let mut rest = [0u8; 16];
+        io.read_exact(&mut rest).await.unwrap();
+        assert_eq!(&rest, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[tokio::test]
+    async fn short_stream_is_not_mistaken_for_a_handshake() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        writer.write_all(&[0x16]).await.unwrap();
+        drop(writer);
+        let mut io = Rewind::new(reader);
+
+        assert!(!io.peek_looks_like_tls_handshake().await.unwrap());
+
+        let mut rest = [0u8; 1];
+        io.read_exact(&mut rest).await.unwrap();
+        assert_eq!(rest, [0x16]);
+    }
+}
@@ -0,0 +1,139 @@
+// Implementation to upgrade TLS streams using the system's native TLS library.
+
+use std::pin::Pin;
+use std::future::Future;
+use std::io;
+use std::task::{Context, Poll};
+
+use hyper::rt::{Read as Read, Write as Write};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_native_tls::{native_tls, TlsConnector};
+
+use crate::{NegotiatedAlpn, TlsUpgrader, TlsMode, MaybeTls};
+use crate::io_adapter_tokio::TokioCompat;
+use crate::rewind::Rewind;
+
+/// The ALPN protocol IDs we advertise by default, in preference order --
+/// matching [`crate::tls_rustls_tokio`]'s default so the two backends behave
+/// the same way unless a caller configures otherwise.
+const DEFAULT_ALPN_PROTOCOLS: &[&str] = &["h2", "http/1.1"];
+
+/// A [`TlsUpgrader`] backed by the platform's native TLS library (Schannel,
+/// Secure Transport, or OpenSSL, via the `native-tls` crate), for users who
+/// need the system trust store or a FIPS-validated TLS stack instead of
+/// rustls + webpki-roots.
+#[derive(Clone, Debug)]
+pub struct NativeTlsUpgrader;
+
+impl<I> TlsUpgrader<Rewind<I>> for NativeTlsUpgrader
+where
+    I: Read + Send + AsyncWrite + AsyncRead + Unpin + 'static,
+{
+    type Io = MaybeTls<Rewind<I>, NativeTlsStream<Rewind<I>>>;
+    type Fut = Pin<Box<dyn Future<Output = io::Result<Self::Io>> + Send>>;
+
+    fn upgrade(&self, host: &str, mut io: Rewind<I>, mode: TlsMode) -> Self::Fut {
+        let host_owned = host.to_string();
+
+        Box::pin(async move {
+            if matches!(mode, TlsMode::Plain) {
+                if io.peek_looks_like_tls_handshake().await? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "peer sent a TLS handshake on a connection requested as plaintext",
+                    ));
+                }
+                return Ok(MaybeTls::Plain(io));
+            }
+
+            let mut builder = native_tls::TlsConnector::builder();
+            builder.request_alpns(DEFAULT_ALPN_PROTOCOLS);
+            let connector = builder
+                .build()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let connector = TlsConnector::from(connector);
+
+            let tls = connector
+                .connect(&host_owned, io)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let alpn = tls
+                .get_ref()
+                .negotiated_alpn()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            Ok(MaybeTls::Tls(NativeTlsStream {
+                inner: TokioCompat(tls),
+                alpn,
+            }))
+        })
+    }
+}
+
+/// A completed native-tls handshake, together with its negotiated ALPN
+/// protocol.
+///
+/// `native_tls::TlsStream::negotiated_alpn` hands back a freshly allocated
+/// buffer each time it's called rather than a borrow tied to the
+/// connection's lifetime (unlike rustls's `ConnectionCommon::alpn_protocol`),
+/// so the result is queried once right after the handshake and cached here
+/// for [`NegotiatedAlpn::alpn_protocol`] to hand out references into.
+pub struct NativeTlsStream<S> {
+    /// The underlying TLS stream.
+    inner: TokioCompat<tokio_native_tls::TlsStream<S>>,
+    /// The ALPN protocol negotiated during the handshake, if any.
+    alpn: Option<Vec<u8>>,
+}
+
+impl<S> NegotiatedAlpn for NativeTlsStream<S> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn.as_deref()
+    }
+}
+
+impl<S: AsyncRead + Unpin> Read for NativeTlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Write for NativeTlsStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for NativeTlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for NativeTlsStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -1,50 +1,144 @@
 // Implementation to upgrade TLS stream specifically for Tokio + Rustls.
 
-use std::{pin::Pin, sync::Arc};
+use std::{fmt, pin::Pin, sync::Arc};
 use std::future::Future;
 use std::io;
 
 use hyper::rt::{Read as Read, Write as Write};
+use sha2::{Digest, Sha256};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_rustls::{
-    rustls::{ClientConfig, RootCertStore},
+    rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    },
     TlsConnector,
 };
 use webpki_roots::TLS_SERVER_ROOTS;
 
-use crate::{TlsUpgrader, TlsMode, MaybeTls};
+use crate::{NegotiatedAlpn, TlsUpgrader, TlsMode, MaybeTls};
 use crate::io_adapter_tokio::TokioCompat;
+use crate::rewind::Rewind;
 
+/// The ALPN protocol IDs we advertise by default, in preference order.
+///
+/// Offering `h2` lets a server pick HTTP/2; `ArtiHttpClient` (in
+/// `crate::pool`) falls back to HTTP/1.1 if the server doesn't select it
+/// (or doesn't speak ALPN at all).
+const DEFAULT_ALPN_PROTOCOLS: &[&[u8]] = &[b"h2", b"http/1.1"];
 
-#[derive(Clone, Debug)]
-pub struct TokioRustlsUpgrader;
+/// A client certificate and its private key, for mutual TLS.
+#[derive(Clone)]
+pub struct ClientAuth {
+    /// The certificate chain to present, leaf first.
+    pub cert_chain: Vec<CertificateDer<'static>>,
+    /// The private key matching the leaf certificate.
+    pub key: Arc<PrivateKeyDer<'static>>,
+}
+
+/// A [`TlsUpgrader`] for Tokio + rustls, configurable with ALPN protocols,
+/// a client certificate for mutual TLS, and a set of pinned server
+/// certificates.
+///
+/// This lets callers talk to bridges or directory mirrors whose keys are
+/// known out of band, without trusting the whole webpki root set for them.
+#[derive(Clone, Default)]
+pub struct TokioRustlsUpgrader {
+    /// ALPN protocol IDs to advertise; defaults to `h2` then `http/1.1`.
+    alpn_protocols: Option<Vec<Vec<u8>>>,
+    /// Client certificate to present for mutual TLS, if any.
+    client_auth: Option<ClientAuth>,
+    /// SHA-256 hashes of acceptable leaf SubjectPublicKeyInfo encodings.
+    ///
+    /// If nonempty, a connection is rejected unless the presented leaf
+    /// matches one of these, in addition to passing normal path validation.
+    pinned_spki_sha256: Vec<[u8; 32]>,
+}
 
-impl<I> TlsUpgrader<I> for TokioRustlsUpgrader
+impl fmt::Debug for TokioRustlsUpgrader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokioRustlsUpgrader")
+            .field("alpn_protocols", &self.alpn_protocols)
+            .field("has_client_auth", &self.client_auth.is_some())
+            .field("pinned_spki_sha256", &self.pinned_spki_sha256.len())
+            .finish()
+    }
+}
+
+impl TokioRustlsUpgrader {
+    /// Advertise exactly `protocols` via ALPN instead of the default `h2`/`http/1.1`.
+    pub fn with_alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = Some(protocols);
+        self
+    }
+
+    /// Present `client_auth` during the handshake, for servers that require mutual TLS.
+    pub fn with_client_auth(mut self, client_auth: ClientAuth) -> Self {
+        self.client_auth = Some(client_auth);
+        self
+    }
+
+    /// Reject the connection unless the server's leaf certificate's SPKI hashes
+    /// to one of `pins`, in addition to passing ordinary path validation.
+    pub fn with_pinned_spki_sha256(mut self, pins: Vec<[u8; 32]>) -> Self {
+        self.pinned_spki_sha256 = pins;
+        self
+    }
+}
+
+impl<I> TlsUpgrader<Rewind<I>> for TokioRustlsUpgrader
 where
     I: Read + Send + AsyncWrite + AsyncRead  + Unpin + 'static,
 {
-    type Io = MaybeTls<I, TokioCompat<tokio_rustls::client::TlsStream<I>>>;
+    type Io = MaybeTls<Rewind<I>, TokioCompat<tokio_rustls::client::TlsStream<Rewind<I>>>>;
     type Fut = Pin<Box<dyn Future<Output = io::Result<Self::Io>> + Send>>;
 
-    fn upgrade(&self, host: &str, io: I, mode: TlsMode) -> Self::Fut {
+    fn upgrade(&self, host: &str, mut io: Rewind<I>, mode: TlsMode) -> Self::Fut {
         let host_owned = host.to_string();
+        let this = self.clone();
 
         Box::pin(async move {
             if matches!(mode, TlsMode::Plain) {
+                if io.peek_looks_like_tls_handshake().await? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "peer sent a TLS handshake on a connection requested as plaintext",
+                    ));
+                }
                 return Ok(MaybeTls::Plain(io));
             }
 
             let mut root_cert_store = RootCertStore::empty();
             root_cert_store.extend(TLS_SERVER_ROOTS.iter().cloned());
 
-            let config = ClientConfig::builder()
-                .with_root_certificates(root_cert_store)
-                .with_no_client_auth();
+            let builder = ClientConfig::builder();
+            let mut config = if this.pinned_spki_sha256.is_empty() {
+                let builder = builder.with_root_certificates(root_cert_store);
+                match this.client_auth.clone() {
+                    Some(auth) => builder
+                        .with_client_auth_cert(auth.cert_chain, auth.key.clone_key())
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+                    None => builder.with_no_client_auth(),
+                }
+            } else {
+                let verifier = PinningServerVerifier::new(root_cert_store, this.pinned_spki_sha256.clone())?;
+                let builder = builder.dangerous().with_custom_certificate_verifier(Arc::new(verifier));
+                match this.client_auth.clone() {
+                    Some(auth) => builder
+                        .with_client_auth_cert(auth.cert_chain, auth.key.clone_key())
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+                    None => builder.with_no_client_auth(),
+                }
+            };
+            config.alpn_protocols = this
+                .alpn_protocols
+                .clone()
+                .unwrap_or_else(|| DEFAULT_ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect());
 
             let connector = TlsConnector::from(Arc::new(config));
 
-            let server_name = host_owned
-                .try_into()
+            let server_name = ServerName::try_from(host_owned)
                 .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Bad DNS name"))?;
 
             let tls = connector.connect(server_name, io).await?;
@@ -53,4 +147,86 @@ where
     }
 }
 
+/// Verifies the server certificate chain normally, then additionally checks
+/// that the leaf's SubjectPublicKeyInfo hashes to one of a pinned set.
+#[derive(Debug)]
+struct PinningServerVerifier {
+    /// The ordinary webpki-based verifier, used for path validation and signatures.
+    inner: Arc<dyn ServerCertVerifier>,
+    /// SHA-256 hashes of acceptable leaf SPKI encodings.
+    pins: Vec<[u8; 32]>,
+}
+
+impl PinningServerVerifier {
+    /// Build a pinning verifier that otherwise validates against `roots`.
+    fn new(roots: RootCertStore, pins: Vec<[u8; 32]>) -> io::Result<Self> {
+        let inner = tokio_rustls::rustls::client::WebPkiServerVerifier::builder(Arc::new(roots))
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(Self { inner, pins })
+    }
+}
+
+impl ServerCertVerifier for PinningServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+
+        let hash: [u8; 32] = Sha256::digest(spki_der(end_entity)?).into();
+        if !self.pins.contains(&hash) {
+            return Err(tokio_rustls::rustls::Error::General(
+                "server certificate did not match any pinned SPKI".into(),
+            ));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Extract the raw SubjectPublicKeyInfo bytes from a DER-encoded certificate.
+///
+/// `cert` comes straight from the peer during the handshake, so a malformed
+/// encoding here is a remote input error, not a bug: report it as a
+/// `rustls::Error` rather than panicking.
+fn spki_der(cert: &CertificateDer<'_>) -> Result<Vec<u8>, tokio_rustls::rustls::Error> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(cert.as_ref()).map_err(|e| {
+        tokio_rustls::rustls::Error::General(format!("malformed peer certificate: {e}"))
+    })?;
+    Ok(parsed.tbs_certificate.subject_pki.raw.to_vec())
+}
+
+impl<I> NegotiatedAlpn for TokioCompat<tokio_rustls::client::TlsStream<I>> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.0.get_ref().1.alpn_protocol()
+    }
+}
+
 
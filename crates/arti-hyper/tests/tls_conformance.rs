@@ -0,0 +1,66 @@
+//! Conformance checks shared by every `TlsUpgrader` backend.
+//!
+//! Both backends must short-circuit identically for `TlsMode::Plain`: no TLS
+//! library should be touched, and the caller gets back exactly the `Io` it
+//! handed in, wrapped in `MaybeTls::Plain` -- unless the peer is actually
+//! sending a TLS handshake on that supposedly-plaintext connection, in which
+//! case both backends must reject it instead of silently passing it through.
+//!
+//! A real end-to-end `TlsMode::Tls` handshake between an in-process rustls
+//! server and each backend's client would additionally confirm the two
+//! upgraders agree on the handshake itself (ALPN selection in particular).
+//! That's deliberately not covered here yet: exercising it needs a test
+//! certificate, and this crate has no certificate-generation or PEM-loading
+//! dependency to produce or parse one with. Tracked as a follow-up rather
+//! than faked with an untested dependency.
+
+#![cfg(all(feature = "tokio", feature = "rustls", feature = "native-tls"))]
+
+use arti_hyper::tls_native_tls_tokio::NativeTlsUpgrader;
+use arti_hyper::tls_rustls_tokio::TokioRustlsUpgrader;
+use arti_hyper::rewind::Rewind;
+use arti_hyper::{MaybeTls, TlsMode, TlsUpgrader};
+
+#[tokio::test]
+async fn rustls_backend_short_circuits_plain_mode() {
+    let (client, _server) = tokio::io::duplex(64);
+    let upgraded = TokioRustlsUpgrader::default()
+        .upgrade("example.com", Rewind::new(client), TlsMode::Plain)
+        .await
+        .unwrap();
+    assert!(matches!(upgraded, MaybeTls::Plain(_)));
+}
+
+#[tokio::test]
+async fn native_tls_backend_short_circuits_plain_mode() {
+    let (client, _server) = tokio::io::duplex(64);
+    let upgraded = NativeTlsUpgrader
+        .upgrade("example.com", Rewind::new(client), TlsMode::Plain)
+        .await
+        .unwrap();
+    assert!(matches!(upgraded, MaybeTls::Plain(_)));
+}
+
+#[tokio::test]
+async fn rustls_backend_rejects_a_tls_handshake_on_plain_mode() {
+    let (client, mut server) = tokio::io::duplex(64);
+    tokio::io::AsyncWriteExt::write_all(&mut server, &[0x16, 0x03, 0x01, 0x00, 0x05])
+        .await
+        .unwrap();
+    let result = TokioRustlsUpgrader::default()
+        .upgrade("example.com", Rewind::new(client), TlsMode::Plain)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn native_tls_backend_rejects_a_tls_handshake_on_plain_mode() {
+    let (client, mut server) = tokio::io::duplex(64);
+    tokio::io::AsyncWriteExt::write_all(&mut server, &[0x16, 0x03, 0x01, 0x00, 0x05])
+        .await
+        .unwrap();
+    let result = NativeTlsUpgrader
+        .upgrade("example.com", Rewind::new(client), TlsMode::Plain)
+        .await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,145 @@
+//! Translation between [`Multiaddr`]s and the addresses Tor understands.
+
+use libp2p::core::multiaddr::{Multiaddr, Protocol};
+
+/// The RFC 4648 base32 alphabet (lowercase, no padding), the encoding a
+/// `.onion` address's hostname label uses.
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Base32-encode `bytes`, lowercase and unpadded.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut bits: u32 = 0;
+    let mut bit_count: u32 = 0;
+    for &byte in bytes {
+        bits = (bits << 8) | u32::from(byte);
+        bit_count += 8;
+        while bit_count >= 5 {
+            bit_count -= 5;
+            out.push(BASE32_ALPHABET[((bits >> bit_count) & 0x1f) as usize] as char);
+        }
+    }
+    if bit_count > 0 {
+        out.push(BASE32_ALPHABET[((bits << (5 - bit_count)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Render a v3 onion-service address hash (pubkey + checksum + version, as
+/// carried by [`Protocol::Onion3`]) as the `<56 chars>.onion` hostname
+/// `into_tor_addr` expects -- `Onion3Addr::hash()` is that raw 35-byte blob,
+/// not a string, so it has to be base32-encoded before it's usable as a host.
+fn onion_hash_to_hostname(hash: &[u8; 35]) -> String {
+    format!("{}.onion", base32_encode(hash))
+}
+
+/// Pull a `(host, port)` pair for [`arti_client::IntoTorAddr`] out of a dial address.
+///
+/// Supports `/onion3/<addr>:<port>` and `/dns/<name>/tcp/<port>` (optionally
+/// wrapped in `/tls`), mirroring the two ways a caller would address a
+/// service reachable through Tor: a hidden service, or a clearnet host
+/// reached via an exit relay.
+pub(crate) fn dial_addr_to_host_port(addr: &Multiaddr) -> Option<(String, u16)> {
+    let mut iter = addr.iter();
+    match iter.next()? {
+        Protocol::Onion3(onion) => Some((onion_hash_to_hostname(onion.hash()), onion.port())),
+        Protocol::Dns(host) | Protocol::Dns4(host) | Protocol::Dns6(host) => {
+            match iter.next()? {
+                Protocol::Tcp(port) => Some((host.to_string(), port)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Pull the onion-service nickname to listen under out of a `listen_on` address.
+///
+/// `/onion3/<addr>` names an *already-published* onion address -- libp2p's
+/// multiaddr parser validates it as one (version, checksum, pubkey), not an
+/// arbitrary string, so there's no way to construct one meaning "publish a
+/// new service named X". That vocabulary doesn't fit this case, so listen
+/// addresses use a distinct, single-component convention instead: a bare
+/// `/dns/<nickname>` with nothing following it (unlike the
+/// `/dns/<host>/tcp/<port>` dial form [`dial_addr_to_host_port`] parses).
+/// This is a local convention for naming the service config to publish, not
+/// a request to resolve `nickname` as an actual DNS name.
+pub(crate) fn parse_listen_addr(addr: &Multiaddr) -> Option<String> {
+    let mut iter = addr.iter();
+    let nickname = match iter.next()? {
+        Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name) => name.to_string(),
+        _ => return None,
+    };
+    // A trailing component (e.g. `/tcp/<port>`) means this is a dial
+    // address, not a listen-under-nickname address.
+    if iter.next().is_some() {
+        return None;
+    }
+    Some(nickname)
+}
+
+#[cfg(test)]
+mod test {
+    // @@ begin test lint list maintained by maint/add_warning @@
+    #![allow(clippy::bool_assert_comparison)]
+    #![allow(clippy::clone_on_copy)]
+    #![allow(clippy::dbg_macro)]
+    #![allow(clippy::mixed_attributes_style)]
+    #![allow(clippy::print_stderr)]
+    #![allow(clippy::print_stdout)]
+    #![allow(clippy::single_char_pattern)]
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::unchecked_duration_subtraction)]
+    #![allow(clippy::useless_vec)]
+    #![allow(clippy::needless_pass_by_value)]
+    //! <!-- @@ end test lint list maintained by maint/add_warning @@ -->
+    use super::*;
+
+    #[test]
+    fn dial_addr_onion3_base32_encodes_the_hash() {
+        let hash = [0u8; 35];
+        let addr = Multiaddr::empty().with(Protocol::Onion3((hash, 1234).into()));
+        let (host, port) = dial_addr_to_host_port(&addr).unwrap();
+        assert_eq!(host, format!("{}.onion", "a".repeat(56)));
+        assert_eq!(port, 1234);
+    }
+
+    #[test]
+    fn dial_addr_dns_tcp() {
+        let addr: Multiaddr = "/dns/example.com/tcp/443".parse().unwrap();
+        let (host, port) = dial_addr_to_host_port(&addr).unwrap();
+        assert_eq!(host, "example.com");
+        assert_eq!(port, 443);
+    }
+
+    #[test]
+    fn dial_addr_dns_without_tcp_is_rejected() {
+        let addr: Multiaddr = "/dns/example.com".parse().unwrap();
+        assert!(dial_addr_to_host_port(&addr).is_none());
+    }
+
+    #[test]
+    fn dial_addr_unsupported_protocol_is_rejected() {
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/1234".parse().unwrap();
+        assert!(dial_addr_to_host_port(&addr).is_none());
+    }
+
+    #[test]
+    fn listen_addr_bare_dns_is_a_nickname() {
+        let addr: Multiaddr = "/dns/my-service".parse().unwrap();
+        assert_eq!(parse_listen_addr(&addr).as_deref(), Some("my-service"));
+    }
+
+    #[test]
+    fn listen_addr_with_trailing_component_is_rejected() {
+        let addr: Multiaddr = "/dns/my-service/tcp/1234".parse().unwrap();
+        assert!(parse_listen_addr(&addr).is_none());
+    }
+
+    #[test]
+    fn listen_addr_onion3_is_rejected() {
+        let hash = [1u8; 35];
+        let addr = Multiaddr::empty().with(Protocol::Onion3((hash, 1234).into()));
+        assert!(parse_listen_addr(&addr).is_none());
+    }
+}
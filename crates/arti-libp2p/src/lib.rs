@@ -0,0 +1,169 @@
+//! A [`libp2p::core::Transport`] backed by an Arti [`TorClient`].
+//!
+//! This lets a libp2p swarm dial and listen on the Tor network directly,
+//! without shelling out to a control port and a SOCKS proxy. Outbound dials
+//! go through [`TorClient::connect`]; inbound connections come from an onion
+//! service published with [`TorClient::launch_onion_service`].
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt as _, StreamExt as _};
+
+use libp2p::core::multiaddr::{Multiaddr, Protocol};
+use libp2p::core::transport::{ListenerId, TransportError, TransportEvent};
+use libp2p::core::Transport;
+
+use arti_client::{IntoTorAddr, TorClient};
+use tor_hsservice::{HsNickname, OnionServiceConfig, RunningOnionService};
+use tor_proto::client::stream::DataStream;
+use tor_rtcompat::Runtime;
+
+mod addr;
+
+use addr::{dial_addr_to_host_port, parse_listen_addr};
+
+/// A [`Transport`] that dials and listens over Tor via an embedded [`TorClient`].
+///
+/// One `TorTransport` can have several listeners (one per call to
+/// [`listen_on`](Transport::listen_on)), each backing a distinct onion
+/// service. Dropping the last handle to a listener's
+/// [`RunningOnionService`] tears the service down.
+pub struct TorTransport<R: Runtime> {
+    /// The underlying Tor client used for both dialing and publishing services.
+    client: TorClient<R>,
+    /// Listeners we've been asked to run, keyed by [`ListenerId`].
+    listeners: Vec<Listener>,
+}
+
+/// Bookkeeping for one active [`listen_on`](Transport::listen_on) call.
+struct Listener {
+    /// The id libp2p uses to refer to this listener.
+    id: ListenerId,
+    /// The address we report ourselves as listening on.
+    addr: Multiaddr,
+    /// Keeps the onion service published; dropped on listener close.
+    _service: RunningOnionService,
+    /// Inbound rendezvous streams, adapted into [`TransportEvent`]s as they arrive.
+    incoming: BoxStream<'static, io::Result<DataStream>>,
+    /// Whether this listener's [`TransportEvent::NewAddress`] still needs to
+    /// be emitted. Set on creation, cleared the first time `poll` reports it;
+    /// a `Swarm` never learns an onion address was added otherwise.
+    new_address_pending: bool,
+}
+
+impl<R: Runtime> TorTransport<R> {
+    /// Wrap `client` in a transport that dials and listens through it.
+    pub fn new(client: TorClient<R>) -> Self {
+        Self {
+            client,
+            listeners: Vec::new(),
+        }
+    }
+}
+
+impl<R: Runtime> Transport for TorTransport<R> {
+    type Output = DataStream;
+    type Error = io::Error;
+    type ListenerUpgrade = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        let nickname = parse_listen_addr(&addr)
+            .ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+
+        let nickname = HsNickname::new(nickname)
+            .map_err(|e| TransportError::Other(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+        let config = OnionServiceConfig::builder()
+            .nickname(nickname)
+            .build()
+            .map_err(|e| TransportError::Other(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+
+        let (service, rendezvous) = self
+            .client
+            .launch_onion_service(config)
+            .map_err(|e| TransportError::Other(io::Error::new(io::ErrorKind::Other, e)))?;
+
+        let onion_addr = Multiaddr::empty().with(Protocol::Onion3(
+            service.onion_address().to_string().into(),
+        ));
+
+        let incoming = rendezvous
+            .map(|stream_request| {
+                stream_request
+                    .accept_data()
+                    .map(|res| res.map_err(|e| io::Error::new(io::ErrorKind::Other, e)))
+            })
+            .flatten_unordered(None)
+            .boxed();
+
+        self.listeners.push(Listener {
+            id,
+            addr: onion_addr,
+            _service: service,
+            incoming,
+            new_address_pending: true,
+        });
+        Ok(())
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        let before = self.listeners.len();
+        self.listeners.retain(|l| l.id != id);
+        self.listeners.len() != before
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+        _opts: libp2p::core::transport::DialOpts,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let (host, port) = dial_addr_to_host_port(&addr)
+            .ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+        let client = self.client.clone();
+
+        Ok(Box::pin(async move {
+            let target = (host.as_str(), port)
+                .into_tor_addr()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            client
+                .connect(target)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()))
+        }))
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let this = self.get_mut();
+        for listener in &mut this.listeners {
+            if listener.new_address_pending {
+                listener.new_address_pending = false;
+                return Poll::Ready(TransportEvent::NewAddress {
+                    listener_id: listener.id,
+                    listen_addr: listener.addr.clone(),
+                });
+            }
+        }
+        for listener in &mut this.listeners {
+            if let Poll::Ready(Some(stream)) = listener.incoming.poll_next_unpin(cx) {
+                return Poll::Ready(TransportEvent::Incoming {
+                    listener_id: listener.id,
+                    upgrade: Box::pin(async move { stream }),
+                    local_addr: listener.addr.clone(),
+                    send_back_addr: listener.addr.clone(),
+                });
+            }
+        }
+        Poll::Pending
+    }
+}
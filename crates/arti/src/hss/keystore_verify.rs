@@ -0,0 +1,188 @@
+//! The `hss keystore-verify` subcommand.
+//!
+//! Checks that every key an onion service's keystore holds is internally
+//! consistent -- every blinded identity key actually derives from the
+//! service's identity key, and every introduction point has both halves of
+//! its key pair -- and flags any keystore entry that isn't one of these
+//! recognized kinds.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use tor_hscrypto::pk::HsIdKeypair;
+use tor_hscrypto::time::TimePeriod;
+use tor_keymgr::hsskeystore::{self, HsKeystoreEntry, HsKeystoreEntryId};
+use tor_llcrypto::pk::ed25519;
+
+/// Something wrong with a service's keystore: not a parse failure, but a
+/// cryptographic or structural inconsistency.
+#[derive(Debug)]
+pub(crate) enum VerifyProblem {
+    /// A stored blinded identity key doesn't derive from the service's
+    /// identity key for the time period its file name claims.
+    BadBlindIdKeyDerivation {
+        /// The claimed time period, as written in the entry's file name.
+        period: String,
+    },
+    /// An introduction point has a signing key but no matching ntor key, or
+    /// vice versa.
+    UnpairedIptKey {
+        /// The introduction point's subject id.
+        subject_id: String,
+        /// Which half is missing: `"ntor"` or `"signing"`.
+        missing: &'static str,
+    },
+}
+
+impl fmt::Display for VerifyProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyProblem::BadBlindIdKeyDerivation { period } => write!(
+                f,
+                "blinded identity key for period {period} does not derive from the service's identity key"
+            ),
+            VerifyProblem::UnpairedIptKey {
+                subject_id,
+                missing,
+            } => write!(
+                f,
+                "introduction point {subject_id} is missing its {missing} key"
+            ),
+        }
+    }
+}
+
+/// The result of verifying one service's keystore.
+#[derive(Debug, Default)]
+pub(crate) struct VerifyReport {
+    /// Cryptographic or structural problems found.
+    pub(crate) problems: Vec<VerifyProblem>,
+    /// Keystore entries that don't parse as any recognized kind.
+    pub(crate) unrecognized: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether the keystore is internally consistent.
+    ///
+    /// Unrecognized entries don't affect this: they're surfaced as a
+    /// warning, not treated as a failure, since a keystore directory can
+    /// reasonably hold files this command doesn't know about.
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Write a human-readable report to `out`.
+    pub(crate) fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for problem in &self.problems {
+            writeln!(out, "error: {problem}")?;
+        }
+        for name in &self.unrecognized {
+            writeln!(out, "warning: unrecognized keystore entry {name:?}")?;
+        }
+        if self.is_consistent() {
+            writeln!(out, "keystore is consistent")?;
+        }
+        Ok(())
+    }
+}
+
+/// Verify the keystore for `nickname`, addressed by `keystore_uri`.
+pub(crate) fn verify(keystore_uri: &str, nickname: &str) -> io::Result<VerifyReport> {
+    let backend = hsskeystore::open(keystore_uri, nickname)?;
+    let mut report = VerifyReport::default();
+
+    let id_keypair = match backend.get(&HsKeystoreEntryId::IdentityKey)? {
+        Some(HsKeystoreEntry::Ed25519Expanded(k)) => Some(k),
+        _ => None,
+    };
+
+    let mut sids = Vec::new();
+    let mut ntors = Vec::new();
+
+    for entry_id in backend.list()? {
+        match entry_id {
+            HsKeystoreEntryId::IdentityKey => {}
+            HsKeystoreEntryId::BlindIdKey { ref period } => {
+                let consistent = id_keypair.as_ref().zip(parse_time_period(period)).is_some_and(
+                    |(id_keypair, time_period)| {
+                        backend
+                            .get(&entry_id)
+                            .ok()
+                            .flatten()
+                            .and_then(|entry| match entry {
+                                HsKeystoreEntry::Ed25519Expanded(k) => Some(k),
+                                _ => None,
+                            })
+                            .is_some_and(|stored| {
+                                blind_id_key_matches(id_keypair, time_period, &stored)
+                            })
+                    },
+                );
+                if !consistent {
+                    report.problems.push(VerifyProblem::BadBlindIdKeyDerivation {
+                        period: period.clone(),
+                    });
+                }
+            }
+            HsKeystoreEntryId::IptSigningKey { subject_id } => sids.push(subject_id),
+            HsKeystoreEntryId::IptNtorKey { subject_id } => ntors.push(subject_id),
+        }
+    }
+
+    for subject_id in &sids {
+        if !ntors.contains(subject_id) {
+            report.problems.push(VerifyProblem::UnpairedIptKey {
+                subject_id: subject_id.clone(),
+                missing: "ntor",
+            });
+        }
+    }
+    for subject_id in &ntors {
+        if !sids.contains(subject_id) {
+            report.problems.push(VerifyProblem::UnpairedIptKey {
+                subject_id: subject_id.clone(),
+                missing: "signing",
+            });
+        }
+    }
+
+    report.unrecognized = backend.list_unrecognized()?;
+
+    Ok(report)
+}
+
+/// Parse a `ks_hs_blind_id+<period>` file name's period back into a
+/// [`TimePeriod`], matching `TimePeriod`'s own
+/// `<interval_num>_<length_minutes>_<epoch_offset_minutes>` `Display` format.
+fn parse_time_period(period: &str) -> Option<TimePeriod> {
+    let mut parts = period.split('_');
+    let interval_num: u64 = parts.next()?.parse().ok()?;
+    let length_minutes: u32 = parts.next()?.parse().ok()?;
+    let epoch_offset_minutes: i32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(TimePeriod::from_parts(
+        interval_num,
+        length_minutes,
+        epoch_offset_minutes,
+    ))
+}
+
+/// Check whether `stored`, the blinded identity key on disk for
+/// `time_period`, is the one actually derived from `id_keypair`.
+///
+/// Delegates the blinding computation itself to `tor-hscrypto`, the same
+/// derivation the onion-service publisher relies on -- this only compares
+/// results, rather than re-implementing the blinding scheme.
+fn blind_id_key_matches(
+    id_keypair: &ed25519::ExpandedKeypair,
+    time_period: TimePeriod,
+    stored: &ed25519::ExpandedKeypair,
+) -> bool {
+    let Ok((expected, _)) = HsIdKeypair::from(id_keypair.clone()).compute_blinded_key(time_period)
+    else {
+        return false;
+    };
+    expected.as_ref().public().to_bytes() == stored.public().to_bytes()
+}
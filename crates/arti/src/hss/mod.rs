@@ -0,0 +1,19 @@
+//! The `hss` subcommand group.
+
+pub(crate) mod keystore_verify;
+
+use std::io;
+
+/// Run `hss -n <nickname> keystore-verify`, writing its report to `stdout`.
+///
+/// Returns the process exit code: `0` if the keystore is consistent (even
+/// with unrecognized-entry warnings), nonzero otherwise.
+pub(crate) fn run_keystore_verify(
+    keystore_uri: &str,
+    nickname: &str,
+    stdout: &mut impl io::Write,
+) -> io::Result<i32> {
+    let report = keystore_verify::verify(keystore_uri, nickname)?;
+    report.write(stdout)?;
+    Ok(if report.is_consistent() { 0 } else { 1 })
+}
@@ -9,9 +9,10 @@
 use crate::hss::util::{
     ARTI_KEYSTORE_POPULATION, CFG_CTOR_PATH, CFG_PATH, CTorMigrateCmd, EXPECTED_ID_KEY_PATH,
     EXPECTED_UNRECOGNIZED_KEYSTORE_ENTRY, HSS_DIR_PATH, IPTS_DIR_PATH, KEYSTORE_DIR_PATH,
-    OnionAddressCmdBuilder, SERVICE_DIR_PATH, UNRECOGNIZED_DIR_PATH, UNRECOGNIZED_SERVICE_ID_PATH,
-    UNRECOGNIZED_SERVICE_PATH,
+    KeystoreVerifyCmdBuilder, OnionAddressCmdBuilder, SERVICE_DIR_PATH, UNRECOGNIZED_DIR_PATH,
+    UNRECOGNIZED_SERVICE_ID_PATH, UNRECOGNIZED_SERVICE_PATH,
 };
+use hex::encode as hex_encode;
 
 mod util;
 
@@ -116,6 +117,49 @@ fn ctor_migrate_is_idempotent() {
     assert!(error.contains("error: Service allium-cepa was already migrated."))
 }
 
+#[test]
+fn ctor_migrate_never_echoes_private_key_material() {
+    // This only checks that the secrets never leak through stdout or
+    // stderr, hex-encoded or raw -- it would pass even if the intermediate
+    // buffers holding decoded private key bytes weren't zeroized at all.
+    // `get_reads_key_bytes_into_a_zeroizing_buffer` and
+    // `put_encodes_key_bytes_into_a_zeroizing_buffer` in `tor-keymgr`'s
+    // `hsskeystore::file` tests cover the read and encode buffers of that
+    // file backend; `ctor-migrate` does not read or write keys through that
+    // backend, so its own buffers are not covered by them.
+    let migrate_cmd = CTorMigrateCmd::new();
+    migrate_cmd.populate_state_dir();
+
+    let private_key_paths = [
+        EXPECTED_ID_KEY_PATH,
+        "keystore/hss/allium-cepa/ipts/k_sid+ce8514e2fe016e4705b064f2226a7628f4226e9a15d28607112e4eac3b3a012f.ed25519_private",
+        "keystore/hss/allium-cepa/ipts/k_hss_ntor+ce8514e2fe016e4705b064f2226a7628f4226e9a15d28607112e4eac3b3a012f.x25519_private",
+    ];
+    let secrets: Vec<Vec<u8>> = private_key_paths
+        .iter()
+        .map(|p| CTorMigrateCmd::original_keystore_entry_bytes(p))
+        .collect();
+
+    let output = migrate_cmd.output().unwrap();
+    assert!(output.status.success());
+
+    let mut combined = output.stdout.clone();
+    combined.extend(output.stderr);
+    let combined_hex = hex_encode(&combined);
+
+    for secret in &secrets {
+        assert!(
+            !combined.windows(secret.len()).any(|w| w == secret.as_slice()),
+            "raw private key material leaked into ctor-migrate output"
+        );
+        let secret_hex = hex_encode(secret);
+        assert!(
+            !combined_hex.contains(&secret_hex),
+            "hex-encoded private key material leaked into ctor-migrate output"
+        );
+    }
+}
+
 #[test]
 fn ctor_migrate_fails_if_applied_to_unregistered_service() {
     let mut cmd = CTorMigrateCmd::new();
@@ -126,3 +170,88 @@ fn ctor_migrate_fails_if_applied_to_unregistered_service() {
     let error = String::from_utf8(cmd.output().unwrap().stderr).unwrap();
     assert!(error.contains("error: The service identified using `--nickname unregistered` is not configured with any recognized CTor keystore."))
 }
+
+#[test]
+fn keystore_verify_fails_on_unpaired_ipt_key() {
+    let migrate_cmd = CTorMigrateCmd::new();
+    migrate_cmd.populate_state_dir();
+    assert!(migrate_cmd.output().unwrap().status.success());
+
+    // Delete one introduction point's ntor key, leaving its signing key
+    // without a matching pair.
+    let orphaned_sid = "ce8514e2fe016e4705b064f2226a7628f4226e9a15d28607112e4eac3b3a012f";
+    let ntor_key_path = migrate_cmd
+        .state_dir_path()
+        .join("keystore/hss/allium-cepa/ipts")
+        .join(format!("k_hss_ntor+{orphaned_sid}.x25519_private"));
+    std::fs::remove_file(&ntor_key_path).unwrap();
+
+    let verify_cmd = KeystoreVerifyCmdBuilder::default()
+        .config_path(CFG_PATH.to_string())
+        .state_directory(Some(
+            migrate_cmd.state_dir_path().to_string_lossy().to_string(),
+        ))
+        .build()
+        .unwrap();
+    let output = verify_cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!(
+        "introduction point {orphaned_sid} is missing its ntor key"
+    )));
+}
+
+#[test]
+fn keystore_verify_fails_on_bad_blind_id_key_derivation() {
+    let migrate_cmd = CTorMigrateCmd::new();
+    migrate_cmd.populate_state_dir();
+    assert!(migrate_cmd.output().unwrap().status.success());
+
+    // Corrupt one blinded identity key so it no longer derives from the
+    // service's identity key for the period its file name claims.
+    let period = "20326_1440_43200";
+    let blind_id_path = migrate_cmd
+        .state_dir_path()
+        .join("keystore/hss/allium-cepa")
+        .join(format!("ks_hs_blind_id+{period}.ed25519_expanded_private"));
+    let mut bytes = std::fs::read(&blind_id_path).unwrap();
+    bytes[0] ^= 0xff;
+    std::fs::write(&blind_id_path, bytes).unwrap();
+
+    let verify_cmd = KeystoreVerifyCmdBuilder::default()
+        .config_path(CFG_PATH.to_string())
+        .state_directory(Some(
+            migrate_cmd.state_dir_path().to_string_lossy().to_string(),
+        ))
+        .build()
+        .unwrap();
+    let output = verify_cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!(
+        "blinded identity key for period {period} does not derive from the service's identity key"
+    )));
+}
+
+#[test]
+fn keystore_verify_succeeds_on_consistent_keystore_with_warnings() {
+    let migrate_cmd = CTorMigrateCmd::new();
+    migrate_cmd.populate_state_dir();
+    assert!(migrate_cmd.output().unwrap().status.success());
+
+    let verify_cmd = KeystoreVerifyCmdBuilder::default()
+        .config_path(CFG_PATH.to_string())
+        .state_directory(Some(
+            migrate_cmd.state_dir_path().to_string_lossy().to_string(),
+        ))
+        .build()
+        .unwrap();
+    let output = verify_cmd.output().unwrap();
+    // `ctor-migrate` leaves only the identity key behind (no blinded
+    // identity or IPT keys), so there is nothing inconsistent to report and
+    // verification succeeds even though the migrated directory still
+    // contains the unrecognized `herba-spontanea` entry.
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("herba-spontanea"));
+}
@@ -131,6 +131,17 @@ impl CTorMigrateCmd {
         Self::clone_dir(&keystore_path, &self.state_dir_path);
     }
 
+    /// Read the raw bytes of a populated keystore entry, by its path relative to the state directory.
+    ///
+    /// Used to assert that private key material never appears verbatim in
+    /// `ctor-migrate`'s stdout/stderr.
+    pub fn original_keystore_entry_bytes(relative_path: &str) -> Vec<u8> {
+        let path = PathBuf::from_str(KEYSTORE_PATH)
+            .unwrap()
+            .join(relative_path);
+        fs::read(path).unwrap()
+    }
+
     /// Recursively clones the entire contents of the directory `source` into the
     /// directory `destination`.
     ///
@@ -235,3 +246,32 @@ impl OnionAddressCmd {
         cmd.output()
     }
 }
+
+/// A struct that represents the subcommand `hss keystore-verify`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, derive_builder::Builder)]
+pub struct KeystoreVerifyCmd {
+    /// Path to the configuration file supplied as the value of the `-c` flag.
+    config_path: String,
+    /// Optional path to a state directory.
+    /// If `Some`, passed as the value to the `-o` flag.
+    #[builder(default)]
+    state_directory: Option<String>,
+    /// Nickname of the service to verify, defaults to `"allium-cepa"`.
+    #[builder(default = "\"allium-cepa\".to_string()")]
+    nickname: String,
+}
+
+impl KeystoreVerifyCmd {
+    /// Execute the command and return its output as an [`Output`].
+    pub fn output(&self) -> std::io::Result<Output> {
+        let mut cmd = Command::cargo_bin("arti").unwrap();
+        cmd.args(["-c", &self.config_path]);
+        if let Some(state_directory) = &self.state_directory {
+            let opt = create_state_dir_entry(state_directory);
+            cmd.args(["-o", &opt]);
+        }
+        cmd.args(["hss", "-n", &self.nickname, "keystore-verify"]);
+
+        cmd.output()
+    }
+}
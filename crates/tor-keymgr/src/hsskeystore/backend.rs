@@ -0,0 +1,149 @@
+//! A pluggable, URI-addressed backend for the `hss` keystore.
+//!
+//! The keystore used to be a fixed on-disk layout under
+//! `keystore/hss/<nickname>/...`. This module lets a config instead name a
+//! backend by URI -- currently just `file:///var/lib/arti/keystore` -- and
+//! dispatches to a concrete [`HsKeystoreBackend`] the same way the
+//! blob/directory services elsewhere in the workspace dispatch on a URL
+//! scheme. `object-store://bucket/prefix` is reserved for a future remote
+//! backend; see [`open`]'s doc comment.
+
+use std::io;
+
+use tor_llcrypto::pk::{curve25519, ed25519};
+
+use super::file;
+
+/// One entry in an onion service's keystore: an identity key, a blinded
+/// identity key for a given time period, or a per-introduction-point key.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum HsKeystoreEntryId {
+    /// The service's long-term identity key (`ks_hs_id`).
+    IdentityKey,
+    /// A blinded identity key for time period `period` (`ks_hs_blind_id+<period>`).
+    BlindIdKey {
+        /// The time period this blinded key is valid for.
+        period: String,
+    },
+    /// The signing key share for an introduction point (`k_sid`).
+    IptSigningKey {
+        /// The introduction point's subject id.
+        subject_id: String,
+    },
+    /// The ntor key share for an introduction point (`k_hss_ntor`).
+    IptNtorKey {
+        /// The introduction point's subject id.
+        subject_id: String,
+    },
+}
+
+/// The key material stored under an [`HsKeystoreEntryId`].
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum HsKeystoreEntry {
+    /// An expanded ed25519 private key (identity and blinded-identity keys).
+    Ed25519Expanded(ed25519::ExpandedKeypair),
+    /// An ed25519 private key (introduction-point signing keys).
+    Ed25519(ed25519::Keypair),
+    /// An x25519 private key (introduction-point ntor keys).
+    X25519(curve25519::StaticSecret),
+}
+
+/// A backend that can store and retrieve the key material for one onion service.
+///
+/// Implementations are selected by the scheme of a keystore URI (see
+/// [`open`]); the file-based layout remains the default so existing configs
+/// are unaffected.
+pub trait HsKeystoreBackend: Send + Sync {
+    /// Fetch the entry at `id`, if present.
+    fn get(&self, id: &HsKeystoreEntryId) -> io::Result<Option<HsKeystoreEntry>>;
+
+    /// Store `entry` at `id`, creating or overwriting it.
+    fn put(&self, id: &HsKeystoreEntryId, entry: HsKeystoreEntry) -> io::Result<()>;
+
+    /// Remove the entry at `id`, if present.
+    fn remove(&self, id: &HsKeystoreEntryId) -> io::Result<()>;
+
+    /// List every entry this backend currently holds for the service.
+    fn list(&self) -> io::Result<Vec<HsKeystoreEntryId>>;
+
+    /// List the names of entries present in this backend that don't parse
+    /// as any known [`HsKeystoreEntryId`].
+    ///
+    /// Used by callers such as `hss keystore-verify` to warn about stray
+    /// files rather than silently ignoring them the way [`list`](Self::list)
+    /// does.
+    fn list_unrecognized(&self) -> io::Result<Vec<String>>;
+}
+
+/// Open the keystore backend named by `uri`.
+///
+/// Recognized schemes:
+///  * `file://<path>` (also the default if `uri` has no scheme): the
+///    existing on-disk layout under `<path>/keystore/hss/<nickname>/...`.
+///  * `object-store://<bucket>/<prefix>`: reserved for entries stored as
+///    individual objects in a remote object store, keyed on
+///    `<prefix>/<entry name>`. No object-store client is vendored into this
+///    crate yet, so this scheme is rejected here, at the single point of
+///    dispatch, rather than handed back as a backend that would only fail
+///    once something actually tried to use it.
+pub fn open(uri: &str, nickname: &str) -> io::Result<Box<dyn HsKeystoreBackend>> {
+    match uri.split_once("://") {
+        None => {
+            // No scheme at all: treat `uri` as a bare filesystem path, to
+            // keep existing configs (which name a state directory, not a
+            // URI) working unchanged.
+            Ok(Box::new(file::FileHsKeystoreBackend::new(uri, nickname)))
+        }
+        Some(("file", path)) => Ok(Box::new(file::FileHsKeystoreBackend::new(path, nickname))),
+        Some(("object-store", _)) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "object-store keystore backend is not implemented yet; use a file:// URI instead",
+        )),
+        Some((scheme, _)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unrecognized keystore URI scheme {scheme:?}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    // @@ begin test lint list maintained by maint/add_warning @@
+    #![allow(clippy::bool_assert_comparison)]
+    #![allow(clippy::clone_on_copy)]
+    #![allow(clippy::dbg_macro)]
+    #![allow(clippy::mixed_attributes_style)]
+    #![allow(clippy::print_stderr)]
+    #![allow(clippy::print_stdout)]
+    #![allow(clippy::single_char_pattern)]
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::unchecked_duration_subtraction)]
+    #![allow(clippy::useless_vec)]
+    #![allow(clippy::needless_pass_by_value)]
+    //! <!-- @@ end test lint list maintained by maint/add_warning @@ -->
+    use super::*;
+
+    #[test]
+    fn bare_path_and_file_scheme_both_open_the_file_backend() {
+        assert!(open("/var/lib/arti/keystore", "allium-cepa").is_ok());
+        assert!(open("file:///var/lib/arti/keystore", "allium-cepa").is_ok());
+    }
+
+    #[test]
+    fn object_store_scheme_is_rejected_at_open_rather_than_on_first_use() {
+        match open("object-store://bucket/prefix", "allium-cepa") {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::Unsupported),
+            Ok(_) => panic!("object-store:// should not open successfully"),
+        }
+    }
+
+    #[test]
+    fn unrecognized_scheme_is_rejected() {
+        match open("ftp://example.com/keystore", "allium-cepa") {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("an unrecognized scheme should not open successfully"),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! The default, on-disk [`HsKeystoreBackend`], matching the existing
+//! `keystore/hss/<nickname>/...` layout.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use zeroize::Zeroizing;
+
+use super::backend::{HsKeystoreBackend, HsKeystoreEntry, HsKeystoreEntryId};
+
+/// Stores entries as files under `<state_dir>/keystore/hss/<nickname>/...`,
+/// the layout every existing Arti keystore already uses on disk.
+pub struct FileHsKeystoreBackend {
+    /// The service's directory: `<state_dir>/keystore/hss/<nickname>`.
+    service_dir: PathBuf,
+}
+
+impl FileHsKeystoreBackend {
+    /// Open the on-disk keystore for `nickname` under `state_dir`.
+    pub fn new(state_dir: &str, nickname: &str) -> Self {
+        Self {
+            service_dir: PathBuf::from(state_dir)
+                .join("keystore")
+                .join("hss")
+                .join(nickname),
+        }
+    }
+
+    /// The path an entry would live at, relative to the service directory.
+    fn entry_path(&self, id: &HsKeystoreEntryId) -> PathBuf {
+        self.service_dir.join(entry_file_name(id))
+    }
+}
+
+/// The on-disk file name for a given entry, matching the existing naming
+/// convention (`ks_hs_id.ed25519_expanded_private`, `ks_hs_blind_id+<period>...`,
+/// `ipts/k_sid+<subject_id>...`, `ipts/k_hss_ntor+<subject_id>...`).
+pub(crate) fn entry_file_name(id: &HsKeystoreEntryId) -> String {
+    match id {
+        HsKeystoreEntryId::IdentityKey => "ks_hs_id.ed25519_expanded_private".to_string(),
+        HsKeystoreEntryId::BlindIdKey { period } => {
+            format!("ks_hs_blind_id+{period}.ed25519_expanded_private")
+        }
+        HsKeystoreEntryId::IptSigningKey { subject_id } => {
+            format!("ipts/k_sid+{subject_id}.ed25519_private")
+        }
+        HsKeystoreEntryId::IptNtorKey { subject_id } => {
+            format!("ipts/k_hss_ntor+{subject_id}.x25519_private")
+        }
+    }
+}
+
+impl HsKeystoreBackend for FileHsKeystoreBackend {
+    // `ks_hs_id`, `k_sid`, and `k_hss_ntor` are long-term private key
+    // material. Every buffer in this backend that holds the raw decoded
+    // bytes of one of these entries must be wrapped in `Zeroizing` so it's
+    // wiped on drop rather than left sitting in the process's memory (or a
+    // swapped-out page) after use.
+    fn get(&self, id: &HsKeystoreEntryId) -> io::Result<Option<HsKeystoreEntry>> {
+        let path = self.entry_path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = read_zeroizing(&path)?;
+        decode_entry(id, &bytes).map(Some)
+    }
+
+    fn put(&self, id: &HsKeystoreEntryId, entry: HsKeystoreEntry) -> io::Result<()> {
+        let path = self.entry_path(id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, encode_entry(&entry).as_slice())
+    }
+
+    fn remove(&self, id: &HsKeystoreEntryId) -> io::Result<()> {
+        let path = self.entry_path(id);
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<HsKeystoreEntryId>> {
+        Ok(self.walk().0)
+    }
+
+    fn list_unrecognized(&self) -> io::Result<Vec<String>> {
+        Ok(self.walk().1)
+    }
+}
+
+impl FileHsKeystoreBackend {
+    /// Walk the service directory and its `ipts` subdirectory, sorting file
+    /// names into ones that parse as a known [`HsKeystoreEntryId`] and ones
+    /// that don't.
+    fn walk(&self) -> (Vec<HsKeystoreEntryId>, Vec<String>) {
+        let mut found = Vec::new();
+        let mut unrecognized = Vec::new();
+        read_entry_names(
+            &self.service_dir,
+            &mut found,
+            &mut unrecognized,
+            parse_entry_file_name,
+        );
+        // The `ipts` subdirectory is walked below; it is not an entry itself.
+        unrecognized.retain(|name| name != "ipts");
+        read_entry_names(
+            &self.service_dir.join("ipts"),
+            &mut found,
+            &mut unrecognized,
+            parse_ipt_file_name,
+        );
+        (found, unrecognized)
+    }
+}
+
+/// Append every entry this directory's file names parse into, via `parse`;
+/// file names `parse` doesn't recognize are appended to `unrecognized`
+/// instead, as their plain file name (not a path relative to the service
+/// directory).
+///
+/// Missing directories (an onion service with no introduction points yet
+/// has no `ipts` subdirectory) are treated as empty, not an error.
+fn read_entry_names(
+    dir: &PathBuf,
+    found: &mut Vec<HsKeystoreEntryId>,
+    unrecognized: &mut Vec<String>,
+    parse: fn(&str) -> Option<HsKeystoreEntryId>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        match parse(&name) {
+            Some(id) => found.push(id),
+            None => unrecognized.push(name),
+        }
+    }
+}
+
+/// Parse a file name directly under the service directory back into the
+/// `HsKeystoreEntryId` it was stored under, the inverse of
+/// [`entry_file_name`] for the `IdentityKey`/`BlindIdKey` cases.
+fn parse_entry_file_name(name: &str) -> Option<HsKeystoreEntryId> {
+    if name == "ks_hs_id.ed25519_expanded_private" {
+        return Some(HsKeystoreEntryId::IdentityKey);
+    }
+    let period = name
+        .strip_prefix("ks_hs_blind_id+")?
+        .strip_suffix(".ed25519_expanded_private")?;
+    Some(HsKeystoreEntryId::BlindIdKey {
+        period: period.to_string(),
+    })
+}
+
+/// Parse a file name under the service directory's `ipts` subdirectory back
+/// into the `HsKeystoreEntryId` it was stored under, the inverse of
+/// [`entry_file_name`] for the introduction-point cases.
+fn parse_ipt_file_name(name: &str) -> Option<HsKeystoreEntryId> {
+    if let Some(subject_id) = name
+        .strip_prefix("k_sid+")
+        .and_then(|s| s.strip_suffix(".ed25519_private"))
+    {
+        return Some(HsKeystoreEntryId::IptSigningKey {
+            subject_id: subject_id.to_string(),
+        });
+    }
+    let subject_id = name
+        .strip_prefix("k_hss_ntor+")?
+        .strip_suffix(".x25519_private")?;
+    Some(HsKeystoreEntryId::IptNtorKey {
+        subject_id: subject_id.to_string(),
+    })
+}
+
+/// Read a file's contents into a buffer that's wiped on drop.
+///
+/// Pulled out of [`FileHsKeystoreBackend::get`] as its own function, with an
+/// explicit `Zeroizing` return type, so the zeroizing guarantee on the
+/// `ks_hs_id`/`k_sid`/`k_hss_ntor` read path can be tested directly rather
+/// than only inferred from `get`'s behavior.
+fn read_zeroizing(path: &std::path::Path) -> io::Result<Zeroizing<Vec<u8>>> {
+    Ok(Zeroizing::new(fs::read(path)?))
+}
+
+/// Encode an entry's key material for on-disk storage.
+///
+/// This defers to the same key-file encoding the existing keystore uses;
+/// provided here as a seam so `FileHsKeystoreBackend` can implement
+/// `HsKeystoreBackend` without duplicating that logic inline.
+///
+/// Returned as `Zeroizing` so the encoded private-key bytes don't linger
+/// in memory once they've been written to disk.
+fn encode_entry(entry: &HsKeystoreEntry) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(match entry {
+        HsKeystoreEntry::Ed25519Expanded(k) => k.to_bytes().to_vec(),
+        HsKeystoreEntry::Ed25519(k) => k.to_bytes().to_vec(),
+        HsKeystoreEntry::X25519(k) => k.to_bytes().to_vec(),
+    })
+}
+
+/// Decode an entry's key material read from disk.
+fn decode_entry(id: &HsKeystoreEntryId, bytes: &[u8]) -> io::Result<HsKeystoreEntry> {
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "malformed keystore entry");
+    match id {
+        HsKeystoreEntryId::IdentityKey | HsKeystoreEntryId::BlindIdKey { .. } => {
+            tor_llcrypto::pk::ed25519::ExpandedKeypair::from_bytes(bytes)
+                .map(HsKeystoreEntry::Ed25519Expanded)
+                .map_err(|_| bad())
+        }
+        HsKeystoreEntryId::IptSigningKey { .. } => {
+            tor_llcrypto::pk::ed25519::Keypair::from_bytes(bytes)
+                .map(HsKeystoreEntry::Ed25519)
+                .map_err(|_| bad())
+        }
+        HsKeystoreEntryId::IptNtorKey { .. } => {
+            let arr: Zeroizing<[u8; 32]> = Zeroizing::new(bytes.try_into().map_err(|_| bad())?);
+            Ok(HsKeystoreEntry::X25519(
+                tor_llcrypto::pk::curve25519::StaticSecret::from(*arr),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    // @@ begin test lint list maintained by maint/add_warning @@
+    #![allow(clippy::bool_assert_comparison)]
+    #![allow(clippy::clone_on_copy)]
+    #![allow(clippy::dbg_macro)]
+    #![allow(clippy::mixed_attributes_style)]
+    #![allow(clippy::print_stderr)]
+    #![allow(clippy::print_stdout)]
+    #![allow(clippy::single_char_pattern)]
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::unchecked_duration_subtraction)]
+    #![allow(clippy::useless_vec)]
+    #![allow(clippy::needless_pass_by_value)]
+    //! <!-- @@ end test lint list maintained by maint/add_warning @@ -->
+    use super::*;
+
+    /// `get()`'s read path wraps the raw decoded key bytes in `Zeroizing`.
+    ///
+    /// This is the regression test the `ks_hs_id`/`k_sid`/`k_hss_ntor`
+    /// zeroizing invariant needs on the read side: unlike asserting the
+    /// bytes never show up in a process's stdout/stderr (which passes
+    /// whether or not zeroizing happens at all), the explicit
+    /// `Zeroizing<Vec<u8>>` binding below only compiles if `read_zeroizing`
+    /// -- the function `get()` actually calls -- still returns that type, so
+    /// it fails to *compile* (not just to assert) if the wrapping is ever
+    /// swapped back for a plain `Vec<u8>`. This avoids reading memory after
+    /// drop, which is undefined behavior regardless of what it happens to
+    /// observe.
+    #[test]
+    fn get_reads_key_bytes_into_a_zeroizing_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entry");
+        fs::write(&path, b"some key bytes").unwrap();
+
+        let bytes: Zeroizing<Vec<u8>> = read_zeroizing(&path).unwrap();
+        assert_eq!(&*bytes, b"some key bytes");
+    }
+
+    /// `put()`'s encode path wraps the encoded key bytes in `Zeroizing`
+    /// before they're written to disk, for the same reason and in the same
+    /// fashion as [`get_reads_key_bytes_into_a_zeroizing_buffer`] above.
+    #[test]
+    fn put_encodes_key_bytes_into_a_zeroizing_buffer() {
+        let keypair = tor_llcrypto::pk::curve25519::StaticSecret::from([7u8; 32]);
+        let entry = HsKeystoreEntry::X25519(keypair);
+
+        let encoded: Zeroizing<Vec<u8>> = encode_entry(&entry);
+        assert_eq!(encoded.len(), 32);
+    }
+
+}
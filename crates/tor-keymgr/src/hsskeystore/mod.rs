@@ -0,0 +1,6 @@
+//! The `hss` keystore abstraction: a [`HsKeystoreBackend`] selectable by URI.
+
+mod backend;
+mod file;
+
+pub use backend::{open, HsKeystoreBackend, HsKeystoreEntry, HsKeystoreEntryId};
@@ -0,0 +1,271 @@
+use criterion::{criterion_group, criterion_main, measurement::Measurement, Criterion, Throughput};
+use criterion_cycles_per_byte::CyclesPerByte;
+use rand::prelude::*;
+
+#[cfg(feature = "counter-galois-onion")]
+use aes::{Aes128Dec, Aes128Enc, Aes256Dec, Aes256Enc};
+use tor_bytes::SecretBuf;
+use tor_llcrypto::{
+    cipher::aes::{Aes128Ctr, Aes256Ctr},
+    d::{Sha1, Sha3_256},
+};
+#[cfg(feature = "counter-galois-onion")]
+use tor_proto::bench_utils::cgo;
+use tor_proto::bench_utils::{
+    tor1, InboundClientCryptWrapper, OutboundClientCryptWrapper, RelayBody, RelayCryptState,
+};
+
+/// Helper macro to set up a middle-relay forwarding benchmark: a cell
+/// travels through this hop in each direction, but the hop never
+/// originates a cell of its own.
+macro_rules! middle_relay_setup {
+    ($client_state_construct: path, $relay_state_construct: path) => {{
+        let seed1: SecretBuf = b"hidden we are free".to_vec().into();
+        let seed2: SecretBuf = b"free to speak, to free ourselves".to_vec().into();
+
+        // The benched hop is the first of two, so the cell it decrypts
+        // still carries a second, unrecognized layer underneath.
+        let relay_state = $relay_state_construct(seed1.clone()).unwrap();
+
+        let mut cc_out = OutboundClientCryptWrapper::new();
+        let state1 = $client_state_construct(seed1).unwrap();
+        cc_out.add_layer(state1);
+        let state2 = $client_state_construct(seed2).unwrap();
+        cc_out.add_layer(state2);
+
+        let mut rng = rand::rng();
+        let mut cell = [0u8; 509];
+        rng.fill(&mut cell[..]);
+        let mut cell: RelayBody = cell.into();
+        cc_out.encrypt(&mut cell, 1).unwrap();
+        (cell, relay_state)
+    }};
+}
+
+/// Benchmark a middle relay forwarding a cell in both directions:
+/// stripping its layer from a cell headed toward the exit, then adding
+/// its layer back onto a cell headed toward the client, without ever
+/// originating a cell itself (that's what the `exit_encrypt` benchmark measures).
+pub fn middle_relay_forward_benchmark(c: &mut Criterion<impl Measurement>) {
+    // Group for the Tor1 relay crypto with 498 bytes of data per relay cell.
+    let mut group = c.benchmark_group("middle_relay_forward");
+    group.throughput(Throughput::Bytes(498));
+
+    group.bench_function("Tor1RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                middle_relay_setup!(
+                    tor1::Tor1ClientCryptState::<Aes128Ctr, Sha1>::construct,
+                    tor1::Tor1RelayCryptState::<Aes128Ctr, Sha1>::construct
+                )
+            },
+            |(cell, relay_state)| {
+                relay_state.decrypt(cell);
+                relay_state.encrypt(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.bench_function("Tor1Hsv3RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                middle_relay_setup!(
+                    tor1::Tor1ClientCryptState::<Aes256Ctr, Sha3_256>::construct,
+                    tor1::Tor1RelayCryptState::<Aes256Ctr, Sha3_256>::construct
+                )
+            },
+            |(cell, relay_state)| {
+                relay_state.decrypt(cell);
+                relay_state.encrypt(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+
+    // Group for the Counter-Galois-Onion relay crypto with ~488 bytes of data per relay cell.
+    let mut group = c.benchmark_group("middle_relay_forward");
+    group.throughput(Throughput::Bytes(488));
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes128", |b| {
+        b.iter_batched_ref(
+            || {
+                middle_relay_setup!(
+                    cgo::CgoClientCryptState::<Aes128Dec, Aes128Enc>::construct,
+                    cgo::CgoRelayCryptState::<Aes128Enc, Aes128Enc>::construct
+                )
+            },
+            |(cell, relay_state)| {
+                relay_state.decrypt(cell);
+                relay_state.encrypt(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes256", |b| {
+        b.iter_batched_ref(
+            || {
+                middle_relay_setup!(
+                    cgo::CgoClientCryptState::<Aes256Dec, Aes256Enc>::construct,
+                    cgo::CgoRelayCryptState::<Aes256Enc, Aes256Enc>::construct
+                )
+            },
+            |(cell, relay_state)| {
+                relay_state.decrypt(cell);
+                relay_state.encrypt(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
+/// Helper macro to set up a full three-hop round trip: a client sends a
+/// cell to the exit and gets a reply back, through the same three
+/// [`RelayCryptState`]s the reply travels through.
+macro_rules! round_trip_setup {
+    ($client_state_construct: path, $relay_state_construct: path) => {{
+        let seed1: SecretBuf = b"hidden we are free".to_vec().into();
+        let seed2: SecretBuf = b"free to speak, to free ourselves".to_vec().into();
+        let seed3: SecretBuf = b"free to hide no more".to_vec().into();
+
+        let relay_state1 = $relay_state_construct(seed1.clone()).unwrap();
+        let relay_state2 = $relay_state_construct(seed2.clone()).unwrap();
+        let relay_state3 = $relay_state_construct(seed3.clone()).unwrap();
+
+        let mut cc_out = OutboundClientCryptWrapper::new();
+        cc_out.add_layer($client_state_construct(seed1.clone()).unwrap());
+        cc_out.add_layer($client_state_construct(seed2.clone()).unwrap());
+        cc_out.add_layer($client_state_construct(seed3.clone()).unwrap());
+
+        let mut cc_in = InboundClientCryptWrapper::new();
+        cc_in.add_layer($client_state_construct(seed1).unwrap());
+        cc_in.add_layer($client_state_construct(seed2).unwrap());
+        cc_in.add_layer($client_state_construct(seed3).unwrap());
+
+        let mut rng = rand::rng();
+        let mut cell = [0u8; 509];
+        rng.fill(&mut cell[..]);
+        let mut cell: RelayBody = cell.into();
+        cc_out.encrypt(&mut cell, 2).unwrap();
+        (cell, relay_state1, relay_state2, relay_state3, cc_in)
+    }};
+}
+
+/// Benchmark a full client-to-exit-and-back round trip: the client
+/// addresses a cell to the third hop, each relay peels its layer in
+/// turn, the exit originates a reply, each relay adds its layer back on
+/// the way out, and the client strips all three layers again.
+pub fn round_trip_benchmark(c: &mut Criterion<impl Measurement>) {
+    // Group for the Tor1 relay crypto with 498 bytes of data per relay cell.
+    let mut group = c.benchmark_group("round_trip");
+    group.throughput(Throughput::Bytes(498));
+
+    group.bench_function("Tor1RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                round_trip_setup!(
+                    tor1::Tor1ClientCryptState::<Aes128Ctr, Sha1>::construct,
+                    tor1::Tor1RelayCryptState::<Aes128Ctr, Sha1>::construct
+                )
+            },
+            |(cell, relay_state1, relay_state2, relay_state3, cc_in)| {
+                relay_state1.decrypt(cell);
+                relay_state2.decrypt(cell);
+                relay_state3.decrypt(cell);
+                relay_state3.originate(cell);
+                relay_state2.encrypt(cell);
+                relay_state1.encrypt(cell);
+                cc_in.decrypt(cell).unwrap();
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.bench_function("Tor1Hsv3RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                round_trip_setup!(
+                    tor1::Tor1ClientCryptState::<Aes256Ctr, Sha3_256>::construct,
+                    tor1::Tor1RelayCryptState::<Aes256Ctr, Sha3_256>::construct
+                )
+            },
+            |(cell, relay_state1, relay_state2, relay_state3, cc_in)| {
+                relay_state1.decrypt(cell);
+                relay_state2.decrypt(cell);
+                relay_state3.decrypt(cell);
+                relay_state3.originate(cell);
+                relay_state2.encrypt(cell);
+                relay_state1.encrypt(cell);
+                cc_in.decrypt(cell).unwrap();
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+
+    // Group for the Counter-Galois-Onion relay crypto with ~488 bytes of data per relay cell.
+    let mut group = c.benchmark_group("round_trip");
+    group.throughput(Throughput::Bytes(488));
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes128", |b| {
+        b.iter_batched_ref(
+            || {
+                round_trip_setup!(
+                    cgo::CgoClientCryptState::<Aes128Dec, Aes128Enc>::construct,
+                    cgo::CgoRelayCryptState::<Aes128Enc, Aes128Enc>::construct
+                )
+            },
+            |(cell, relay_state1, relay_state2, relay_state3, cc_in)| {
+                relay_state1.decrypt(cell);
+                relay_state2.decrypt(cell);
+                relay_state3.decrypt(cell);
+                relay_state3.originate(cell);
+                relay_state2.encrypt(cell);
+                relay_state1.encrypt(cell);
+                cc_in.decrypt(cell).unwrap();
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes256", |b| {
+        b.iter_batched_ref(
+            || {
+                round_trip_setup!(
+                    cgo::CgoClientCryptState::<Aes256Dec, Aes256Enc>::construct,
+                    cgo::CgoRelayCryptState::<Aes256Enc, Aes256Enc>::construct
+                )
+            },
+            |(cell, relay_state1, relay_state2, relay_state3, cc_in)| {
+                relay_state1.decrypt(cell);
+                relay_state2.decrypt(cell);
+                relay_state3.decrypt(cell);
+                relay_state3.originate(cell);
+                relay_state2.encrypt(cell);
+                relay_state1.encrypt(cell);
+                cc_in.decrypt(cell).unwrap();
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    name = circuit_relay;
+    config = Criterion::default()
+       .with_measurement(CyclesPerByte)
+       .sample_size(5000);
+    targets = middle_relay_forward_benchmark, round_trip_benchmark);
+criterion_main!(circuit_relay);
@@ -117,10 +117,197 @@ pub fn relay_decrypt_benchmark(c: &mut Criterion<impl Measurement>) {
     group.finish();
 }
 
+/// Helper macro to set up a client-side onion-encryption benchmark over `$n_hops` layers.
+macro_rules! client_encrypt_setup {
+    ($client_state_construct: path, $n_hops: expr) => {{
+        let mut cc_out = OutboundClientCryptWrapper::new();
+        for hop in 0..$n_hops {
+            let seed: SecretBuf = format!("hidden we are free, hop {hop}").into_bytes().into();
+            let state = $client_state_construct(seed).unwrap();
+            cc_out.add_layer(state);
+        }
+
+        let mut rng = rand::rng();
+        let mut cell = [0u8; 509];
+        rng.fill(&mut cell[..]);
+        let cell: RelayBody = cell.into();
+        (cell, cc_out)
+    }};
+}
+
+/// Benchmark a client onion-encrypting a cell addressed to the `n_hops`-th hop,
+/// for circuits of length 1 to 4, to show the per-hop cost of layering.
+pub fn client_encrypt_benchmark(c: &mut Criterion<impl Measurement>) {
+    for n_hops in 1..=4u8 {
+        // Group for the Tor1 relay crypto with 498 bytes of data per relay cell.
+        let mut group = c.benchmark_group("client_encrypt");
+        group.throughput(Throughput::Bytes(498));
+
+        group.bench_function(format!("Tor1RelayCrypto_{n_hops}hop"), |b| {
+            b.iter_batched_ref(
+                || client_encrypt_setup!(tor1::Tor1ClientCryptState::<Aes128Ctr, Sha1>::construct, n_hops),
+                |(cell, cc_out)| {
+                    cc_out.encrypt(cell, n_hops - 1).unwrap();
+                },
+                criterion::BatchSize::SmallInput,
+            );
+        });
+
+        group.bench_function(format!("Tor1Hsv3RelayCrypto_{n_hops}hop"), |b| {
+            b.iter_batched_ref(
+                || {
+                    client_encrypt_setup!(
+                        tor1::Tor1ClientCryptState::<Aes256Ctr, Sha3_256>::construct,
+                        n_hops
+                    )
+                },
+                |(cell, cc_out)| {
+                    cc_out.encrypt(cell, n_hops - 1).unwrap();
+                },
+                criterion::BatchSize::SmallInput,
+            );
+        });
+
+        group.finish();
+
+        // Group for the Counter-Galois-Onion relay crypto with ~488 bytes of data per relay cell.
+        let mut group = c.benchmark_group("client_encrypt");
+        group.throughput(Throughput::Bytes(488));
+
+        #[cfg(feature = "counter-galois-onion")]
+        group.bench_function(format!("CGO_Aes128_{n_hops}hop"), |b| {
+            b.iter_batched_ref(
+                || {
+                    client_encrypt_setup!(
+                        cgo::CgoClientCryptState::<Aes128Dec, Aes128Enc>::construct,
+                        n_hops
+                    )
+                },
+                |(cell, cc_out)| {
+                    cc_out.encrypt(cell, n_hops - 1).unwrap();
+                },
+                criterion::BatchSize::SmallInput,
+            );
+        });
+
+        #[cfg(feature = "counter-galois-onion")]
+        group.bench_function(format!("CGO_Aes256_{n_hops}hop"), |b| {
+            b.iter_batched_ref(
+                || {
+                    client_encrypt_setup!(
+                        cgo::CgoClientCryptState::<Aes256Dec, Aes256Enc>::construct,
+                        n_hops
+                    )
+                },
+                |(cell, cc_out)| {
+                    cc_out.encrypt(cell, n_hops - 1).unwrap();
+                },
+                criterion::BatchSize::SmallInput,
+            );
+        });
+
+        group.finish();
+    }
+}
+
+/// Benchmark a relay originating a cell to send backward, toward the client
+/// (the complement of [`relay_decrypt_benchmark`]'s forward direction).
+pub fn relay_encrypt_benchmark(c: &mut Criterion<impl Measurement>) {
+    // Group for the Tor1 relay crypto with 498 bytes of data per relay cell.
+    let mut group = c.benchmark_group("relay_encrypt");
+    group.throughput(Throughput::Bytes(498));
+
+    group.bench_function("Tor1RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                let seed: SecretBuf = b"hidden we are free".to_vec().into();
+                let relay_state =
+                    tor1::Tor1RelayCryptState::<Aes128Ctr, Sha1>::construct(seed).unwrap();
+                let mut rng = rand::rng();
+                let mut cell = [0u8; 509];
+                rng.fill(&mut cell[..]);
+                let cell: RelayBody = cell.into();
+                (cell, relay_state)
+            },
+            |(cell, relay_state)| {
+                relay_state.originate(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.bench_function("Tor1Hsv3RelayCrypto", |b| {
+        b.iter_batched_ref(
+            || {
+                let seed: SecretBuf = b"hidden we are free".to_vec().into();
+                let relay_state =
+                    tor1::Tor1RelayCryptState::<Aes256Ctr, Sha3_256>::construct(seed).unwrap();
+                let mut rng = rand::rng();
+                let mut cell = [0u8; 509];
+                rng.fill(&mut cell[..]);
+                let cell: RelayBody = cell.into();
+                (cell, relay_state)
+            },
+            |(cell, relay_state)| {
+                relay_state.originate(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+
+    // Group for the Counter-Galois-Onion relay crypto with ~488 bytes of data per relay cell.
+    let mut group = c.benchmark_group("relay_encrypt");
+    group.throughput(Throughput::Bytes(488));
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes128", |b| {
+        b.iter_batched_ref(
+            || {
+                let seed: SecretBuf = b"hidden we are free".to_vec().into();
+                let relay_state =
+                    cgo::CgoRelayCryptState::<Aes128Enc, Aes128Enc>::construct(seed).unwrap();
+                let mut rng = rand::rng();
+                let mut cell = [0u8; 509];
+                rng.fill(&mut cell[..]);
+                let cell: RelayBody = cell.into();
+                (cell, relay_state)
+            },
+            |(cell, relay_state)| {
+                relay_state.originate(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    #[cfg(feature = "counter-galois-onion")]
+    group.bench_function("CGO_Aes256", |b| {
+        b.iter_batched_ref(
+            || {
+                let seed: SecretBuf = b"hidden we are free".to_vec().into();
+                let relay_state =
+                    cgo::CgoRelayCryptState::<Aes256Enc, Aes256Enc>::construct(seed).unwrap();
+                let mut rng = rand::rng();
+                let mut cell = [0u8; 509];
+                rng.fill(&mut cell[..]);
+                let cell: RelayBody = cell.into();
+                (cell, relay_state)
+            },
+            |(cell, relay_state)| {
+                relay_state.originate(cell);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     name = relay_decrypt;
     config = Criterion::default()
        .with_measurement(CyclesPerByte)
        .sample_size(5000);
-    targets = relay_decrypt_benchmark);
+    targets = relay_decrypt_benchmark, client_encrypt_benchmark, relay_encrypt_benchmark);
 criterion_main!(relay_decrypt);
@@ -0,0 +1,150 @@
+//! The client-visible [`DataStream`], used to read and write application
+//! data over a Tor stream.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures::io::{AsyncRead as FutAsyncRead, AsyncWrite as FutAsyncWrite};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::stream::{DataReader, DataWriter};
+
+use super::FlushPolicy;
+
+/// A bidirectional data stream over the Tor network, usable with any
+/// `AsyncRead`/`AsyncWrite`-based protocol implementation.
+///
+/// By default this buffers outbound writes internally and only pushes them
+/// onto the circuit when the buffer fills or the caller calls `poll_flush`.
+/// Use [`set_flush_policy`](Self::set_flush_policy) to change that for
+/// callers (such as some TLS backends) that expect a socket-like stream.
+pub struct DataStream {
+    /// The reader half of this stream.
+    r: DataReader,
+    /// The writer half of this stream.
+    w: DataWriter,
+    /// How eagerly this stream pushes buffered writes onto the circuit.
+    flush_policy: FlushPolicy,
+    /// Set when a write is still owed a flush under `flush_policy`: that
+    /// flush is completed before the next write (flush-after-write
+    /// policies) or before the next read (flush-before-read policies).
+    flush_owed: bool,
+}
+
+impl DataStream {
+    /// Wrap a reader/writer pair as a `DataStream`.
+    pub(crate) fn new(r: DataReader, w: DataWriter) -> Self {
+        DataStream {
+            r,
+            w,
+            flush_policy: FlushPolicy::default(),
+            flush_owed: false,
+        }
+    }
+
+    /// Change how eagerly this stream flushes buffered writes onto the
+    /// circuit.
+    ///
+    /// This can be changed at any point in the stream's lifetime; it takes
+    /// effect starting with the next read or write.
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.flush_policy = policy;
+    }
+
+    /// Finish flushing any write still owed a flush under `flush_policy`.
+    fn poll_flush_if_owed(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.flush_owed {
+            ready!(Pin::new(&mut self.w).poll_flush(cx))?;
+            self.flush_owed = false;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl FutAsyncRead for DataStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.flush_policy.flush_before_read() {
+            ready!(this.poll_flush_if_owed(cx))?;
+        }
+        Pin::new(&mut this.r).poll_read(cx, buf)
+    }
+}
+
+impl FutAsyncWrite for DataStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.flush_policy.flush_after_write() {
+            ready!(this.poll_flush_if_owed(cx))?;
+        }
+        let n = ready!(Pin::new(&mut this.w).poll_write(cx, buf))?;
+        if this.flush_policy.flush_after_write() || this.flush_policy.flush_before_read() {
+            this.flush_owed = true;
+        }
+        if this.flush_policy.flush_after_write() {
+            // Push this write out now; whatever can't go out yet is finished
+            // before the next write.
+            if let Poll::Ready(res) = this.poll_flush_if_owed(cx) {
+                res?;
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(Pin::new(&mut this.w).poll_flush(cx))?;
+        this.flush_owed = false;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().w).poll_close(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncRead for DataStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.flush_policy.flush_before_read() {
+            ready!(this.poll_flush_if_owed(cx))?;
+        }
+        let n = ready!(Pin::new(&mut this.r).poll_read(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncWrite for DataStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        FutAsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        FutAsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        FutAsyncWrite::poll_close(self, cx)
+    }
+}
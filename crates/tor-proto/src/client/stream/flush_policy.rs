@@ -0,0 +1,51 @@
+//! Flush behavior for [`DataStream`](super::DataStream).
+//!
+//! By default a `DataStream` buffers outbound bytes and only sends them on
+//! to the circuit when the buffer fills or the caller calls `poll_flush`
+//! explicitly. Some protocol implementations (notably TLS libraries using
+//! platform-native backends, such as Secure Transport on macOS) assume a
+//! socket-like stream that flushes every write and is flushed before every
+//! read; against a buffering `DataStream` they can stall indefinitely.
+//! Rather than have every such caller wrap the stream in their own
+//! `AsyncRead`/`AsyncWrite` adapter, a `DataStream` can be told to apply one
+//! of these policies itself.
+
+/// Governs when a [`DataStream`](super::DataStream) pushes buffered data
+/// onto its circuit.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum FlushPolicy {
+    /// Flush only when the internal buffer is full or the caller calls
+    /// `poll_flush`. This is the default, and is the most efficient policy
+    /// for bulk transfer.
+    #[default]
+    Buffered,
+    /// Flush immediately after every successful write.
+    ///
+    /// Needed by TLS backends that expect a write to reach the peer before
+    /// they consider the write "done" (e.g. to complete a handshake flight).
+    FlushOnWrite,
+    /// Flush before every read.
+    ///
+    /// Needed by protocols that write a request and then read a response
+    /// without an explicit flush in between.
+    FlushBeforeRead,
+    /// Flush after every write, and before every read.
+    ///
+    /// The combination TLS backends that treat the stream as a bare socket
+    /// typically need: every flight they write must reach the peer, and
+    /// every read must first push out anything still queued.
+    Immediate,
+}
+
+impl FlushPolicy {
+    /// Whether this policy requires flushing before a read is attempted.
+    pub(crate) fn flush_before_read(&self) -> bool {
+        matches!(self, FlushPolicy::FlushBeforeRead | FlushPolicy::Immediate)
+    }
+
+    /// Whether this policy requires flushing after a write completes.
+    pub(crate) fn flush_after_write(&self) -> bool {
+        matches!(self, FlushPolicy::FlushOnWrite | FlushPolicy::Immediate)
+    }
+}
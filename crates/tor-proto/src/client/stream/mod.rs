@@ -0,0 +1,7 @@
+//! Client-side data streams.
+
+mod data_stream;
+mod flush_policy;
+
+pub use data_stream::DataStream;
+pub use flush_policy::FlushPolicy;
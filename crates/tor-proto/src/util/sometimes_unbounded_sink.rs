@@ -59,14 +59,15 @@ use pin_project::pin_project;
 /// After an error has been reported, there may still be buffered data,
 /// which will only be delivered if `SometimesUnboundedSink` is polled again
 /// (and the error in the underlying sink was transient).
-//
-// TODO circpad: Depending on what we need to add in order to implement circuit padding,
-// we might need to allow `buf` to hold a certain capacity even in response
-// to regular bounded send.  (In other words, when the sink is full,
-// we'd let people queue up to N items on our buf with a regular poll_ready.)
-//
-// But we won't build that if we don't have to. This logic will need to be changed anyway
-// when we finally implement circuit muxes.
+///
+/// ### Reserved capacity
+///
+/// A `SometimesUnboundedSink` constructed with [`new_with_reserve`](Self::new_with_reserve)
+/// additionally lets a *bounded* `poll_ready`/`start_send` succeed even
+/// when the inner sink is full, as long as fewer than `reserve` items are
+/// already queued. This gives a small fixed-size burst allowance without
+/// going fully unbounded, which circuit padding needs: a padding cell must
+/// be queueable even if the circuit's regular data sink is momentarily full.
 #[pin_project]
 pub(crate) struct SometimesUnboundedSink<T, S> {
     /// Things we couldn't send_unbounded right away
@@ -79,8 +80,6 @@ pub(crate) struct SometimesUnboundedSink<T, S> {
     ///  * If this is nonempty, the executor knows to wake this task.
     ///    This is achieved as follows:
     ///    If this is nonempty, `inner.poll_ready()` has been called.
-    ///
-    ///    XXXX no longer true; what to say instead?
     buf: VecDeque<T>,
 
     /// If true, we should behave as if the underlying sink is blocked,
@@ -110,6 +109,19 @@ pub(crate) struct SometimesUnboundedSink<T, S> {
     ///  * This can only transition from Some to None by waking it.
     waker: Option<Waker>,
 
+    /// The number of items a bounded `poll_ready` is allowed to admit into
+    /// `buf` even while `inner` is not ready.
+    ///
+    /// 0 unless constructed with a nonzero reserve via
+    /// [`new_with_reserve`](Self::new_with_reserve); at 0, a bounded send
+    /// never queues onto `buf`.
+    reserve: usize,
+
+    /// Set by the most recent `poll_ready` that returned `Ready(Ok(()))`
+    /// while `inner` was actually *not* ready, to tell the next `start_send`
+    /// to queue the item onto `buf` rather than forward it to `inner`.
+    reserve_grant: bool,
+
     /// The actual sink
     ///
     /// This also has the relevant `Waker`.
@@ -141,11 +153,24 @@ impl<T, S: Sink<T>> SometimesUnboundedSink<T, S> {
     // There is no method for unwrapping.  If we make this type more public,
     // there should be, but that method will need `where S: Unpin`.
     pub(crate) fn new(inner: S) -> Self {
+        Self::new_with_reserve(inner, 0)
+    }
+
+    /// Wrap an inner `Sink` with a `SometimesUnboundedSink`, allowing a
+    /// bounded `poll_ready`/`start_send` to queue up to `reserve` items onto
+    /// `buf` even while `inner` is not ready.
+    ///
+    /// This is for callers (such as circuit padding) that need a small,
+    /// fixed-size burst allowance on the regular bounded-send path, without
+    /// going fully unbounded via [`send_unbounded`](Self::send_unbounded).
+    pub(crate) fn new_with_reserve(inner: S, reserve: usize) -> Self {
         SometimesUnboundedSink {
             buf: VecDeque::new(),
             blocked: false,
             n_flush_bypass: 0,
             waker: None,
+            reserve,
+            reserve_grant: false,
             inner,
         }
     }
@@ -169,7 +194,6 @@ impl<T, S: Sink<T>> SometimesUnboundedSink<T, S> {
         item: T,
     ) -> Result<(), S::Error> {
         match self.as_mut().poll_ready(cx) {
-            // Waker invariant: poll_ready only returns Ready(Ok(())) if `buf` is empty
             Ready(Ok(())) => self.as_mut().start_send(item),
             // Waker invariant: if we report an error, we're then allowed to expect polling again
             Ready(Err(e)) => Err(e),
@@ -268,15 +292,64 @@ impl<T, S: Sink<T>> SometimesUnboundedSink<T, S> {
 impl<T, S: Sink<T>> Sink<T> for SometimesUnboundedSink<T, S> {
     type Error = S::Error;
 
-    // Only returns `Ready(Ok(()))` if `buf` is empty
+    // Returns `Ready(Ok(()))` either because `flush_buf` fully drained `buf`
+    // and `inner` is ready for a new item directly, or — with reserve
+    // capacity configured and still available — because `buf` (whatever of
+    // it `flush_buf` couldn't drain) has room left for one more.
     fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
-        ready!(self.as_mut().flush_buf(cx))?;
-        self.project().inner.poll_ready(cx)
+        match self.as_mut().flush_buf(cx) {
+            Ready(Ok(())) => {
+                // `buf` is empty: nothing is waiting ahead of a new item,
+                // so it's safe to ask `inner` for it directly.
+                let mut self_ = self.as_mut().project();
+                match self_.inner.as_mut().poll_ready(cx) {
+                    Ready(result) => {
+                        *self_.reserve_grant = false;
+                        Ready(result)
+                    }
+                    // Waker invariant: inner gave Pending, so the task has
+                    // been recorded for wakeup, whether or not we grant the
+                    // reserve below.
+                    Pending => {
+                        if *self_.reserve > 0 {
+                            *self_.reserve_grant = true;
+                            Ready(Ok(()))
+                        } else {
+                            Pending
+                        }
+                    }
+                }
+            }
+            Ready(Err(e)) => Ready(Err(e)),
+            // `buf` is nonempty (blocked, or `inner` wasn't ready for its
+            // front item — either way `flush_buf` has already asked `inner`
+            // or recorded our waker, satisfying the Waker invariant). A new
+            // item must queue behind what's already there, to preserve
+            // order, as long as there's still reserve room for it.
+            Pending => {
+                if self.buf.len() < self.reserve {
+                    *self.as_mut().project().reserve_grant = true;
+                    Ready(Ok(()))
+                } else {
+                    Pending
+                }
+            }
+        }
     }
 
     fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), S::Error> {
-        assert!(self.buf.is_empty(), "start_send without poll_ready");
-        self.project().inner.start_send(item)
+        let self_ = self.project();
+        if *self_.reserve_grant {
+            assert!(
+                self_.buf.len() < *self_.reserve,
+                "start_send without poll_ready, or reserve exceeded"
+            );
+            self_.buf.push_back(item);
+            Ok(())
+        } else {
+            assert!(self_.buf.is_empty(), "start_send without poll_ready");
+            self_.inner.start_send(item)
+        }
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
@@ -356,4 +429,79 @@ mod test {
             runtime.progress_until_stalled().await;
         });
     }
+
+    #[test]
+    fn reserve() {
+        // Single task throughout, so the interleaving of `feed`/`poll!`
+        // calls against manual drains of `rx` below is fully deterministic
+        // (no concurrent receiver task to race against).
+        MockRuntime::test_with_various(|_runtime| async move {
+            // Buffer 0 plus the one slot every sender is guaranteed gives
+            // `inner` exactly one slot.
+            let (inner, mut rx) = mpsc::channel(0);
+            let mut tx = SometimesUnboundedSink::new_with_reserve(inner, 2);
+            let mut tx = Pin::new(&mut tx);
+            let mut received = Vec::new();
+            let mut recv = |rx: &mut mpsc::Receiver<i32>, received: &mut Vec<i32>| {
+                if let Ok(Some(n)) = rx.try_next() {
+                    received.push(n);
+                }
+            };
+
+            // `inner`'s one slot is free: this goes straight through.
+            tx.as_mut().feed(0).await.unwrap();
+
+            tx.as_mut().set_blocked();
+
+            // `inner`'s slot is now taken by the (unreceived) item 0, but
+            // the reserve covers the next two bounded sends anyway.
+            tx.as_mut().feed(1).await.unwrap();
+            tx.as_mut().feed(2).await.unwrap();
+            assert_eq!(tx.n_queued(), 2);
+
+            // `send_unbounded` always succeeds, even past the reserve.
+            tx.as_mut().send_unbounded(3).await.unwrap();
+            assert_eq!(tx.n_queued(), 3);
+
+            // The backlog now exceeds the reserve, so a further bounded
+            // send must wait until it shrinks back down.
+            let mut feed4 = pin!(tx.as_mut().feed(4));
+            assert!(
+                futures::poll!(feed4.as_mut()).is_pending(),
+                "reserve should be exhausted"
+            );
+
+            // One bypass, plus draining `inner`'s slot, lets exactly one
+            // queued item (item 1) through despite still being blocked.
+            tx.as_mut().allow_flush(1);
+            recv(&mut rx, &mut received);
+            assert!(
+                futures::poll!(feed4.as_mut()).is_pending(),
+                "only one bypassed item should have drained"
+            );
+            assert_eq!(tx.n_queued(), 2);
+
+            tx.as_mut().set_unblocked();
+            recv(&mut rx, &mut received);
+            feed4.await.unwrap();
+
+            // Drain the rest via a manual poll/drain pump: nothing else is
+            // running concurrently to free up `inner` for us.
+            let mut close = pin!(tx.as_mut().close());
+            loop {
+                match futures::poll!(close.as_mut()) {
+                    Ready(r) => {
+                        r.unwrap();
+                        break;
+                    }
+                    Pending => recv(&mut rx, &mut received),
+                }
+            }
+            while let Ok(Some(n)) = rx.try_next() {
+                received.push(n);
+            }
+
+            assert_eq!(received, vec![0, 1, 2, 3, 4]);
+        });
+    }
 }